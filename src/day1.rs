@@ -1,6 +1,11 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::io::{self, BufRead};
+
 // Let's over-engineer this for explicitness.
 type Calories = u64;
-// Ended up not needing this, since both parts just asked for the sums in the backpacks.
+// Both parts just ask for the sums in the backpacks, only the report below looks at the items.
 type ElfBackpack = Vec<Calories>;
 
 #[aoc_generator(day1)]
@@ -15,6 +20,307 @@ fn day1_parse(input: &str) -> Vec<ElfBackpack> {
         .collect()
 }
 
+// Everything below works a group at a time straight off a reader, so the input never has to be in
+// memory all at once (we load test with synthetic files of ~100 million lines). Yields
+// (elf index, total calories) for each blank-line separated group.
+pub struct ElfTotals<R: BufRead> {
+    reader: R,
+    line: String,
+    next_elf: usize,
+    done: bool,
+}
+
+impl<R: BufRead> ElfTotals<R> {
+    pub fn new(reader: R) -> Self {
+        ElfTotals {
+            reader,
+            line: String::new(),
+            next_elf: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ElfTotals<R> {
+    type Item = io::Result<(usize, Calories)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut total: Calories = 0;
+        let mut items = 0;
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => {
+                    self.done = true;
+                    break;
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+            let line = self.line.trim_end();
+            if line.is_empty() {
+                if items == 0 {
+                    //Extra blank lines between groups, don't count them as an elf
+                    continue;
+                }
+                break;
+            }
+            match line.parse::<Calories>() {
+                Ok(n) => total += n,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(io::Error::new(io::ErrorKind::InvalidData, e)));
+                }
+            }
+            items += 1;
+        }
+        if items == 0 {
+            return None;
+        }
+        self.next_elf += 1;
+        Some(Ok((self.next_elf - 1, total)))
+    }
+}
+
+// Keeps the k biggest totals seen so far in a min-heap, so pushing is O(log k) and memory is O(k)
+// no matter how many elves there are
+pub struct TopK {
+    k: usize,
+    heap: BinaryHeap<Reverse<(Calories, Reverse<usize>)>>,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    pub fn push(&mut self, elf: usize, total: Calories) {
+        //Ties go to the earlier elf, hence the Reverse on the index
+        self.heap.push(Reverse((total, Reverse(elf))));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    pub fn sum(&self) -> Calories {
+        self.heap.iter().map(|Reverse((total, _))| total).sum()
+    }
+
+    // (elf index, total), biggest first
+    pub fn into_sorted_vec(self) -> Vec<(usize, Calories)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((total, Reverse(elf)))| (elf, total))
+            .collect()
+    }
+}
+
+pub fn top_k<R: BufRead>(reader: R, k: usize) -> io::Result<Vec<(usize, Calories)>> {
+    let mut top = TopK::new(k);
+    for elf in ElfTotals::new(reader) {
+        let (idx, total) = elf?;
+        top.push(idx, total);
+    }
+    Ok(top.into_sorted_vec())
+}
+
+pub fn top_k_sum<R: BufRead>(reader: R, k: usize) -> io::Result<Calories> {
+    Ok(top_k(reader, k)?.iter().map(|(_, total)| total).sum())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElfStats {
+    pub elf: usize,
+    pub items: usize,
+    pub total: Calories,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramBin {
+    // Half open [low, high), except the last bin which also includes the max
+    pub low: f64,
+    pub high: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub elves: Vec<ElfStats>,
+    pub mean: f64,
+    pub median: f64,
+    pub percentiles: Vec<(u8, f64)>,
+    pub histogram: Vec<HistogramBin>,
+    // Indices into `elves` of anyone outside 1.5 * IQR of the quartiles
+    pub outliers: Vec<usize>,
+}
+
+static REPORT_PERCENTILES: [u8; 7] = [10, 25, 50, 75, 90, 95, 99];
+
+// Linear interpolation between the closest ranks, same as numpy's default. `sorted` can't be empty
+fn percentile(sorted: &[Calories], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] as f64 + (sorted[hi] as f64 - sorted[lo] as f64) * (rank - lo as f64)
+}
+
+pub fn report(input: &[ElfBackpack], bins: usize) -> Report {
+    assert!(!input.is_empty(), "No elves to report on");
+    assert!(bins > 0);
+
+    let elves = input
+        .iter()
+        .enumerate()
+        .map(|(elf, bp)| ElfStats {
+            elf,
+            items: bp.len(),
+            total: bp.iter().sum(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut sorted = elves.iter().map(|e| e.total).collect::<Vec<_>>();
+    sorted.sort();
+
+    let mean = sorted.iter().sum::<Calories>() as f64 / sorted.len() as f64;
+    let median = percentile(&sorted, 50.0);
+    let percentiles = REPORT_PERCENTILES
+        .iter()
+        .map(|p| (*p, percentile(&sorted, *p as f64)))
+        .collect();
+
+    let (min, max) = (sorted[0] as f64, *sorted.last().unwrap() as f64);
+    //Everyone carrying the same amount would make every bin zero width, just stretch it out
+    let width = if max > min {
+        (max - min) / bins as f64
+    } else {
+        1.0
+    };
+    let mut histogram = (0..bins)
+        .map(|b| HistogramBin {
+            low: min + width * b as f64,
+            high: min + width * (b + 1) as f64,
+            count: 0,
+        })
+        .collect::<Vec<_>>();
+    for total in sorted.iter() {
+        let bin = (((*total as f64 - min) / width) as usize).min(bins - 1);
+        histogram[bin].count += 1;
+    }
+
+    let (q1, q3) = (percentile(&sorted, 25.0), percentile(&sorted, 75.0));
+    let iqr = q3 - q1;
+    let outliers = elves
+        .iter()
+        .filter(|e| (e.total as f64) < q1 - 1.5 * iqr || (e.total as f64) > q3 + 1.5 * iqr)
+        .map(|e| e.elf)
+        .collect();
+
+    Report {
+        elves,
+        mean,
+        median,
+        percentiles,
+        histogram,
+        outliers,
+    }
+}
+
+impl Report {
+    // Hand rolled so we don't need to pull serde in for one struct
+    pub fn to_json(&self) -> String {
+        let elves = self
+            .elves
+            .iter()
+            .map(|e| {
+                format!(
+                    "{{\"elf\":{},\"items\":{},\"total\":{}}}",
+                    e.elf, e.items, e.total
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let percentiles = self
+            .percentiles
+            .iter()
+            .map(|(p, v)| format!("\"p{}\":{}", p, v))
+            .collect::<Vec<_>>()
+            .join(",");
+        let histogram = self
+            .histogram
+            .iter()
+            .map(|b| {
+                format!(
+                    "{{\"low\":{},\"high\":{},\"count\":{}}}",
+                    b.low, b.high, b.count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let outliers = self
+            .outliers
+            .iter()
+            .map(|o| o.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"elves\":[{}],\"mean\":{},\"median\":{},\"percentiles\":{{{}}},\"histogram\":[{}],\"outliers\":[{}]}}",
+            elves, self.mean, self.median, percentiles, histogram, outliers
+        )
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>6} | {:>5} | {:>10} |", "elf", "items", "total")?;
+        writeln!(f, "{:-<7}+{:-<7}+{:-<12}+", "", "", "")?;
+        for e in self.elves.iter() {
+            let flag = if self.outliers.contains(&e.elf) {
+                " outlier"
+            } else {
+                ""
+            };
+            writeln!(
+                f,
+                "{:>6} | {:>5} | {:>10} |{}",
+                e.elf, e.items, e.total, flag
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "mean:   {:.1}", self.mean)?;
+        writeln!(f, "median: {:.1}", self.median)?;
+        for (p, v) in self.percentiles.iter() {
+            writeln!(f, "p{:<5} {:.1}", p, v)?;
+        }
+        writeln!(f)?;
+        let biggest = self
+            .histogram
+            .iter()
+            .map(|b| b.count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        for b in self.histogram.iter() {
+            //Scale the bars to at most 40 wide
+            let bar = "#".repeat((b.count * 40).div_ceil(biggest));
+            writeln!(
+                f,
+                "[{:>10.0}, {:>10.0}) {:>5} {}",
+                b.low, b.high, b.count, bar
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[aoc(day1, part1)]
 pub fn part1(input: &Vec<ElfBackpack>) -> u64 {
     input
@@ -26,9 +332,11 @@ pub fn part1(input: &Vec<ElfBackpack>) -> u64 {
 
 #[aoc(day1, part2)]
 pub fn part2(input: &Vec<ElfBackpack>) -> u64 {
-    let mut sums: Vec<u64> = input.iter().map(|bp| bp.iter().sum::<Calories>()).collect();
-    sums.sort();
-    sums.iter().rev().take(3).sum()
+    let mut top = TopK::new(3);
+    for (idx, bp) in input.iter().enumerate() {
+        top.push(idx, bp.iter().sum::<Calories>());
+    }
+    top.sum()
 }
 
 #[cfg(test)]
@@ -62,4 +370,68 @@ mod tests {
     fn day1_part2() {
         assert_eq!(part2(&day1_parse(get_test_input())), 45000);
     }
+
+    #[test]
+    fn day1_report() {
+        let report = report(&day1_parse(get_test_input()), 4);
+        assert_eq!(report.elves.len(), 5);
+        assert_eq!(
+            report.elves[2],
+            ElfStats {
+                elf: 2,
+                items: 2,
+                total: 11000
+            }
+        );
+        assert_eq!(report.mean, 11000.0);
+        assert_eq!(report.median, 10000.0);
+        assert_eq!(report.percentiles[1], (25, 6000.0));
+        assert_eq!(report.percentiles[3], (75, 11000.0));
+        assert_eq!(
+            report.histogram.iter().map(|b| b.count).collect::<Vec<_>>(),
+            vec![2, 2, 0, 1]
+        );
+        //Q1 6000, Q3 11000, so anything over 18500 is out there
+        assert_eq!(report.outliers, vec![3]);
+        assert!(report
+            .to_json()
+            .starts_with("{\"elves\":[{\"elf\":0,\"items\":3,\"total\":6000},"));
+        assert!(report.to_json().ends_with("\"outliers\":[3]}"));
+        assert!(report.to_string().contains("24000 | outlier"));
+    }
+
+    #[test]
+    fn day1_streaming() {
+        let input = get_test_input();
+        assert_eq!(top_k_sum(input.as_bytes(), 1).unwrap(), 24000);
+        assert_eq!(top_k_sum(input.as_bytes(), 3).unwrap(), 45000);
+        assert_eq!(
+            top_k(input.as_bytes(), 3).unwrap(),
+            vec![(3, 24000), (2, 11000), (4, 10000)]
+        );
+        assert_eq!(top_k_sum(input.as_bytes(), 100).unwrap(), 55000);
+        assert!(top_k_sum("1000\nabc\n".as_bytes(), 3).is_err());
+
+        //Bigger generated input, check the heap against just sorting everything
+        let big = (0..5000u64)
+            .map(|elf| {
+                (0..elf % 7 + 1)
+                    .map(|item| ((elf * 7919 + item * 104729) % 10007).to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let mut sums = day1_parse(&big)
+            .iter()
+            .map(|bp| bp.iter().sum::<Calories>())
+            .collect::<Vec<_>>();
+        sums.sort();
+        for k in [1, 3, 10, 250] {
+            assert_eq!(
+                top_k_sum(io::BufReader::new(big.as_bytes()), k).unwrap(),
+                sums.iter().rev().take(k).sum::<Calories>()
+            );
+        }
+    }
 }
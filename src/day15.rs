@@ -3,10 +3,8 @@ use self::lazy_static::lazy_static;
 extern crate regex;
 use self::regex::Regex;
 use std::collections::{HashMap, HashSet};
-extern crate rayon;
-use self::rayon::prelude::*;
-use std::sync::Arc;
-use std::sync::Mutex;
+
+use interval::{Interval, IntervalSet};
 
 use std::convert::TryFrom;
 
@@ -83,6 +81,7 @@ fn manhatten((x1, y1): (i32, i32), (x2, y2): (i32, i32)) -> i32 {
     (x1 - x2).abs() + (y1 - y2).abs()
 }
 
+#[allow(dead_code)]
 fn bounded_naive_determine_sensor_range(
     (s_x, s_y): (i32, i32),
     (b_x, b_y): (i32, i32),
@@ -176,6 +175,7 @@ fn find_sensor_range_intersections(
     unimplemented!();
 }
 
+#[allow(dead_code)]
 fn naive_determine_sensor_range(
     (s_x, s_y): (i32, i32),
     (b_x, b_y): (i32, i32),
@@ -202,30 +202,34 @@ pub fn part1(inp: &InputType) -> OutputType {
 }
 
 
+// What a single sensor rules out on one row: a diamond of radius dist cut at cared_y is just a
+// run of columns centred on the sensor, shrinking by one on each side per row away from it
+fn row_coverage((s_x, s_y): (i32, i32), (b_x, b_y): (i32, i32), cared_y: i32) -> Option<Interval> {
+    let half_width = manhatten((s_x, s_y), (b_x, b_y)) - (s_y - cared_y).abs();
+    if half_width < 0 {
+        return None;
+    }
+    Some(Interval::new(
+        (s_x - half_width) as i64,
+        (s_x + half_width) as i64,
+    ))
+}
+
 pub fn part1_param((map, closest_map): &InputType, cared_y: i32) -> OutputType {
-    let mut all_ranges = Arc::new(Mutex::new(HashSet::new()));
-
-    closest_map
-        .par_iter()
-        .map(|((s_x, s_y), (b_x, b_y))| {
-            naive_determine_sensor_range((*s_x, *s_y), (*b_x, *b_y), cared_y)
-        })
-        .for_each(|h_set| {
-            let ranges = Arc::clone(&all_ranges);
-            let mut new_set = ranges.lock().unwrap();
-            *new_set = (*new_set)
-                .union(&h_set)
-                .map(|x| *x)
-                .collect::<HashSet<(i32, i32)>>();
-        });
-
-    let cant_be = all_ranges.lock().unwrap();
-
-    cant_be
+    //Used to union a HashSet of every covered point per sensor (with rayon and a mutex to try to
+    //make that bearable); merging one interval per sensor is instant
+    let covered = closest_map
         .iter()
-        .filter(|(_, y)| *y == cared_y)
-        .filter(|(x, y)| map.get(&(*x, *y)).is_none())
-        .count() as i32
+        .filter_map(|(sensor, beacon)| row_coverage(*sensor, *beacon, cared_y))
+        .collect::<IntervalSet>();
+
+    //Anything already sitting on that row can't be "no beacon here"
+    let occupied = map
+        .keys()
+        .filter(|(x, y)| *y == cared_y && covered.contains(*x as i64))
+        .count() as u64;
+
+    (covered.covered_len() - occupied) as i32
 }
 
 #[aoc(day15, part2)]
@@ -1,4 +1,13 @@
-type StrategyGuide<'a> = Vec<(Hand<'a>, Hand<'a>)>;
+extern crate anyhow;
+use self::anyhow::{anyhow, bail, Result};
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Just the raw letters from each column; what they mean depends on the rules (and the part)
+type StrategyGuide<'a> = Vec<(&'a str, &'a str)>;
 
 type InputType<'a> = StrategyGuide<'a>;
 type OutputType = u64;
@@ -15,179 +24,452 @@ type OutputType = u64;
 // 3. I would have liked to have done &str for the Hand, but then I have to introduce lifetimes,
 //    and the aoc-runner didn't seem to really like that, or at least, I was having a hard time
 //    parsing the error, so just copy, even though it loses out on performance.
+// 4. Later: the enums are gone entirely. A Hand is just an index into a Rules table, which holds
+//    the "beats" graph, the scores and what each letter means, so the same code plays RPSLS (or
+//    any other cyclic game) straight from a config file.
 
-#[derive(Debug, Clone)]
-pub enum Hand<'a> {
-    //Include the variant (A or Y for each, might not be needed)
-    Rock(&'a str),
-    Paper(&'a str),
-    Scissor(&'a str),
+// Index of a shape in `Rules::shapes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Hand(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Lose,
+    Draw,
+    Win,
 }
 
-impl PartialEq for Hand<'_> {
-    //Would be cool if there was an easier way to do this, but this seems to be the best way to
-    //throw out the additional information (the string variant)
-    fn eq(&self, other: &Self) -> bool {
+impl FromStr for Outcome {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "Lose" => Ok(Outcome::Lose),
+            "Draw" => Ok(Outcome::Draw),
+            "Win" => Ok(Outcome::Win),
+            _ => Err(anyhow!("Unknown outcome {}", s)),
+        }
+    }
+}
+
+impl Outcome {
+    fn index(&self) -> usize {
         match self {
-            Hand::Rock(_) => {
-                if let Hand::Rock(_) = other {
-                    true
-                } else {
-                    false
-                }
-            }
-            Hand::Paper(_) => {
-                if let Hand::Paper(_) = other {
-                    true
-                } else {
-                    false
-                }
-            }
-            Hand::Scissor(_) => {
-                if let Hand::Scissor(_) = other {
-                    true
-                } else {
-                    false
-                }
-            }
+            Outcome::Lose => 0,
+            Outcome::Draw => 1,
+            Outcome::Win => 2,
         }
     }
 }
 
-#[derive(Debug)]
-enum Outcome {
-    Lose,
-    Draw,
-    Win,
+// The regular game, written in the same format as any other rule file
+pub static STANDARD_RULES: &str = "# Rock Paper Scissors
+shapes = Rock Paper Scissors
+shape_scores = 1 2 3
+outcome_scores = 0 3 6
+beats = Rock>Scissors Paper>Rock Scissors>Paper
+opponent = A:Rock B:Paper C:Scissors
+response = X:Rock Y:Paper Z:Scissors
+result = X:Lose Y:Draw Z:Win";
+
+// Rules file format, one `key = values` per line, `#` starts a comment:
+//   shapes          names of every shape
+//   shape_scores    points for throwing each shape, in the same order
+//   outcome_scores  points for losing, drawing and winning
+//   beats           `A>B` pairs, every pair of distinct shapes must appear exactly once
+//   opponent        `letter:Shape` for the first column
+//   response        `letter:Shape` for the second column in part 1
+//   result          `letter:Outcome` for the second column in part 2
+#[derive(Debug, Clone)]
+pub struct Rules {
+    shapes: Vec<String>,
+    // beats[a][b] is true if a beats b
+    beats: Vec<Vec<bool>>,
+    shape_scores: Vec<u64>,
+    outcome_scores: [u64; 3],
+    opponent: HashMap<String, Hand>,
+    response: HashMap<String, Hand>,
+    result: HashMap<String, Outcome>,
 }
 
-//Could maybe do this with some PartialOrd Stuff but lets get simple first
-fn get_outcome(a: &Hand, b: &Hand) -> Outcome {
-    if a == b {
-        return Outcome::Draw;
-    }
-    match a {
-        Hand::Rock(_) => {
-            if let Hand::Scissor(_) = b {
-                Outcome::Lose
-            } else {
-                Outcome::Win
+impl Rules {
+    pub fn standard() -> Self {
+        Rules::from_config(STANDARD_RULES).unwrap()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Rules::from_config(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_config(config: &str) -> Result<Self> {
+        let mut entries = HashMap::new();
+        for line in config.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key = value, got {:?}", line))?;
+            entries.insert(key.trim(), value.split_whitespace().collect::<Vec<_>>());
+        }
+        let mut get = |key: &str| {
+            entries
+                .remove(key)
+                .ok_or_else(|| anyhow!("Missing {} in rules", key))
+        };
+
+        let shapes = get("shapes")?
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let n = shapes.len();
+        if n < 2 {
+            bail!("Need at least two shapes, otherwise nothing can be won or lost");
+        }
+        let shape_idx = |name: &str| {
+            shapes
+                .iter()
+                .position(|s| s == name)
+                .map(Hand)
+                .ok_or_else(|| anyhow!("Unknown shape {}", name))
+        };
+
+        let shape_scores = get("shape_scores")?
+            .iter()
+            .map(|s| s.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if shape_scores.len() != n {
+            bail!("Expected {} shape scores, got {}", n, shape_scores.len());
+        }
+
+        let outcome_scores = get("outcome_scores")?
+            .iter()
+            .map(|s| s.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let outcome_scores: [u64; 3] = match outcome_scores[..] {
+            [lose, draw, win] => [lose, draw, win],
+            _ => bail!("Expected 3 outcome scores (lose, draw, win)"),
+        };
+
+        let mut beats = vec![vec![false; n]; n];
+        for pair in get("beats")? {
+            let (winner, loser) = pair
+                .split_once('>')
+                .ok_or_else(|| anyhow!("Expected Winner>Loser, got {}", pair))?;
+            let (Hand(w), Hand(l)) = (shape_idx(winner)?, shape_idx(loser)?);
+            if w == l {
+                bail!("{} can't beat itself", winner);
             }
+            beats[w][l] = true;
         }
-        Hand::Paper(_) => {
-            if let Hand::Rock(_) = b {
-                Outcome::Lose
-            } else {
-                Outcome::Win
+        //Has to be a tournament, otherwise some pair of throws has no outcome (or two)
+        for a in 0..n {
+            for b in a + 1..n {
+                if beats[a][b] == beats[b][a] {
+                    bail!(
+                        "Exactly one of {} and {} has to beat the other",
+                        shapes[a],
+                        shapes[b]
+                    );
+                }
             }
         }
-        Hand::Scissor(_) => {
-            if let Hand::Paper(_) = b {
-                Outcome::Lose
-            } else {
-                Outcome::Win
+        //Part 2 can ask for any outcome against any shape, so every shape needs something that
+        //beats it and something it beats
+        for a in 0..n {
+            if !(0..n).any(|b| beats[b][a]) {
+                bail!("Nothing beats {}", shapes[a]);
+            }
+            if !beats[a].iter().any(|&b| b) {
+                bail!("{} doesn't beat anything", shapes[a]);
             }
         }
+
+        let opponent = letters(get("opponent")?)?
+            .into_iter()
+            .map(|(l, name)| Ok((l, shape_idx(name)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let response = letters(get("response")?)?
+            .into_iter()
+            .map(|(l, name)| Ok((l, shape_idx(name)?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        let result = letters(get("result")?)?
+            .into_iter()
+            .map(|(l, name)| Ok((l, name.parse::<Outcome>()?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        Ok(Rules {
+            shapes,
+            beats,
+            shape_scores,
+            outcome_scores,
+            opponent,
+            response,
+            result,
+        })
+    }
+
+    pub fn shapes(&self) -> impl DoubleEndedIterator<Item = Hand> {
+        (0..self.shapes.len()).map(Hand)
+    }
+
+    pub fn name(&self, hand: &Hand) -> &str {
+        &self.shapes[hand.0]
+    }
+
+    pub fn opponent_hand(&self, letter: &str) -> Hand {
+        *self
+            .opponent
+            .get(letter)
+            .unwrap_or_else(|| panic!("Unknown char {}", letter))
+    }
+
+    pub fn response_hand(&self, letter: &str) -> Hand {
+        *self
+            .response
+            .get(letter)
+            .unwrap_or_else(|| panic!("Unknown char {}", letter))
+    }
+
+    pub fn result(&self, letter: &str) -> Outcome {
+        *self
+            .result
+            .get(letter)
+            .unwrap_or_else(|| panic!("Unknown char {}", letter))
     }
 }
 
-fn round_outcome(a: &Hand, b: &Hand) -> u64 {
-    let selected_shape_score: u64 = match b {
-        Hand::Rock(_) => 1,
-        Hand::Paper(_) => 2,
-        Hand::Scissor(_) => 3,
-    };
+fn letters(values: Vec<&str>) -> Result<Vec<(String, &str)>> {
+    values
+        .iter()
+        .map(|v| {
+            v.split_once(':')
+                .map(|(letter, name)| (letter.to_string(), name))
+                .ok_or_else(|| anyhow!("Expected letter:Name, got {}", v))
+        })
+        .collect()
+}
 
-    let outcome: u64 = match get_outcome(a, b) {
-        Outcome::Lose => 0,
-        Outcome::Draw => 3,
-        Outcome::Win => 6,
-    };
+// Outcome from b's point of view (b is always us)
+fn get_outcome(rules: &Rules, a: &Hand, b: &Hand) -> Outcome {
+    if a == b {
+        Outcome::Draw
+    } else if rules.beats[b.0][a.0] {
+        Outcome::Win
+    } else {
+        Outcome::Lose
+    }
+}
+
+pub fn round_outcome(rules: &Rules, a: &Hand, b: &Hand) -> u64 {
+    let selected_shape_score = rules.shape_scores[b.0];
+    let outcome = rules.outcome_scores[get_outcome(rules, a, b).index()];
 
     //println!("{:?} {:?} => {:?} + {} = {}", a, b, outcome, selected_shape_score, outcome + selected_shape_score);
 
     outcome + selected_shape_score
 }
 
-//part 2
-impl From<&str> for Outcome {
-    fn from(c: &str) -> Self {
-        match c {
-            "X" => Outcome::Lose,
-            "Y" => Outcome::Draw,
-            "Z" => Outcome::Win,
-            _ => panic!("Unknown char {}", c),
-        }
-    }
-}
-fn convert_hand_to_outcome<'a>(b: &'a Hand) -> Outcome {
-    //heh, since we mistranslated the column, just add a translation shim here You could of course
-    //just not convert across like this and use Hand, but that's kind of gross.
-    // it's really cool that I can use an or expression in a pattern and have it all resolve to the
-    // same variable
-    match b {
-        Hand::Rock(a) | Hand::Scissor(a) | Hand::Paper(a) => (*a).into(),
-    }
-}
-fn determine_play<'a>(a: &'a Hand, b: &Outcome) -> Hand<'a> {
-    match b {
-        Outcome::Draw => a.clone(), //given that the plays are different for the first column, this
-        //technically has an incorrect string variant, but I never
-        //check them so, who cares.
-        Outcome::Win => match a {
-            Hand::Rock(_) => Hand::Paper("Y"),
-            Hand::Paper(_) => Hand::Scissor("Z"),
-            Hand::Scissor(_) => Hand::Rock("X"),
-        },
-        Outcome::Lose => match a {
-            Hand::Rock(_) => Hand::Scissor("Z"),
-            Hand::Paper(_) => Hand::Rock("X"),
-            Hand::Scissor(_) => Hand::Paper("Y"),
-        },
-    }
-}
-
-//Could use &str here, but the macro doesn't really expect to work well with lifetime specifiers,
-//so be lazy for now
-impl<'a> From<&'a str> for Hand<'a> {
-    fn from(c: &'a str) -> Self {
-        match c {
-            "A" | "X" => Hand::Rock(&c),
-            "B" | "Y" => Hand::Paper(&c),
-            "C" | "Z" => Hand::Scissor(&c),
-            _ => panic!("Unknown char {}", c),
-        }
-    }
+// With more than three shapes there can be several throws that get the outcome we want, take the
+// one that scores the most
+fn determine_play(rules: &Rules, a: &Hand, b: &Outcome) -> Hand {
+    rules
+        .shapes()
+        .filter(|h| get_outcome(rules, a, h) == *b)
+        .max_by_key(|h| rules.shape_scores[h.0])
+        .unwrap_or_else(|| panic!("Nothing {:?}s against {}", b, rules.name(a)))
 }
 
 //#[aoc_generator(day2)]; doesn't work with lifetime bound items
-fn day2_parse(input: &str) -> InputType {
+fn day2_parse(input: &str) -> InputType<'_> {
     input
         .split("\n")
         .map(|line| {
-            let mut it = line.split_whitespace().map(|c| c.into());
+            let mut it = line.split_whitespace();
             (it.next().unwrap(), it.next().unwrap())
         })
         .collect()
 }
 
-#[aoc(day2, part1)]
-pub fn part1(input: &str) -> OutputType {
+pub fn part1_with_rules(input: &str, rules: &Rules) -> OutputType {
     day2_parse(input)
         .iter()
-        .map(|(r1, r2)| round_outcome(r1, r2))
+        .map(|(r1, r2)| round_outcome(rules, &rules.opponent_hand(r1), &rules.response_hand(r2)))
         .sum()
 }
 
-#[aoc(day2, part2)]
-pub fn part2(input: &str) -> OutputType {
+pub fn part2_with_rules(input: &str, rules: &Rules) -> OutputType {
     day2_parse(input)
         .iter()
-        .map(|(r1, r2)| (r1, determine_play(&r1, &convert_hand_to_outcome(&r2))))
-        .map(|(r1, r2)| round_outcome(&r1, &r2))
+        .map(|(r1, r2)| {
+            let them = rules.opponent_hand(r1);
+            (them, determine_play(rules, &them, &rules.result(r2)))
+        })
+        .map(|(r1, r2)| round_outcome(rules, &r1, &r2))
         .sum()
 }
 
+// Analysis, for sanity checking a guide before trusting it. Everything reuses round_outcome so it
+// follows whatever rules are loaded
+
+// How often the opponent throws each shape according to the first column, indexed by Hand
+pub fn estimate_opponent(input: &str, rules: &Rules) -> Vec<f64> {
+    let guide = day2_parse(input);
+    let mut counts = vec![0; rules.shapes.len()];
+    for (them, _) in guide.iter() {
+        counts[rules.opponent_hand(them).0] += 1;
+    }
+    counts
+        .iter()
+        .map(|c| *c as f64 / guide.len() as f64)
+        .collect()
+}
+
+// Expected score of one round if they throw from `opponent` and we throw from `strategy`
+pub fn expected_round_score(rules: &Rules, opponent: &[f64], strategy: &[f64]) -> f64 {
+    rules
+        .shapes()
+        .flat_map(|them| rules.shapes().map(move |us| (them, us)))
+        .map(|(them, us)| {
+            opponent[them.0] * strategy[us.0] * round_outcome(rules, &them, &us) as f64
+        })
+        .sum()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseAnalysis {
+    pub opponent: Vec<f64>,
+    // Best single shape to always throw, with its expected score per round
+    pub best_fixed: (Hand, f64),
+    // (their throw, our best answer, score for that round)
+    pub best_per_move: Vec<(Hand, Hand, u64)>,
+}
+
+pub fn analyse_responses(input: &str, rules: &Rules) -> ResponseAnalysis {
+    let opponent = estimate_opponent(input, rules);
+    let pure = |hand: Hand| {
+        let mut strategy = vec![0.0; rules.shapes.len()];
+        strategy[hand.0] = 1.0;
+        strategy
+    };
+
+    //Ties go to the first shape listed
+    let best_fixed = rules
+        .shapes()
+        .map(|us| (us, expected_round_score(rules, &opponent, &pure(us))))
+        .fold(None, |best: Option<(Hand, f64)>, (us, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((us, score)),
+        })
+        .unwrap();
+
+    let best_per_move = rules
+        .shapes()
+        .map(|them| {
+            let (us, score) = rules
+                .shapes()
+                .map(|us| (us, round_outcome(rules, &them, &us)))
+                .rev()
+                .max_by_key(|(_, score)| *score)
+                .unwrap();
+            (them, us, score)
+        })
+        .collect();
+
+    ResponseAnalysis {
+        opponent,
+        best_fixed,
+        best_per_move,
+    }
+}
+
+// xorshift64*, we only need something cheap and repeatable for the simulation, not a new dependency
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        //Zero is a fixed point of xorshift
+        Rng(seed.max(1))
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545F4914F6CDD1D) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn pick(&mut self, weights: &[f64]) -> usize {
+        let mut roll = self.next_f64() * weights.iter().sum::<f64>();
+        for (i, w) in weights.iter().enumerate() {
+            if roll < *w {
+                return i;
+            }
+            roll -= w;
+        }
+        //Only reachable through float rounding, fall back on the last shape that can be picked
+        weights.iter().rposition(|w| *w > 0.0).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonteCarlo {
+    pub trials: usize,
+    // Of the total score for a whole game (as many rounds as the guide has lines)
+    pub mean: f64,
+    pub variance: f64,
+}
+
+// Plays `trials` games against the opponent estimated from the guide, throwing from the mixed
+// `strategy` (weights indexed by Hand, they don't have to add up to 1)
+pub fn monte_carlo(
+    input: &str,
+    rules: &Rules,
+    strategy: &[f64],
+    trials: usize,
+    seed: u64,
+) -> MonteCarlo {
+    assert_eq!(strategy.len(), rules.shapes.len());
+    assert!(trials > 1);
+    assert!(strategy.iter().any(|w| *w > 0.0));
+
+    let rounds = day2_parse(input).len();
+    let opponent = estimate_opponent(input, rules);
+    let mut rng = Rng::new(seed);
+
+    let scores = (0..trials)
+        .map(|_| {
+            (0..rounds)
+                .map(|_| {
+                    let them = Hand(rng.pick(&opponent));
+                    let us = Hand(rng.pick(strategy));
+                    round_outcome(rules, &them, &us)
+                })
+                .sum::<u64>() as f64
+        })
+        .collect::<Vec<_>>();
+
+    let mean = scores.iter().sum::<f64>() / trials as f64;
+    let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (trials - 1) as f64;
+
+    MonteCarlo {
+        trials,
+        mean,
+        variance,
+    }
+}
+
+#[aoc(day2, part1)]
+pub fn part1(input: &str) -> OutputType {
+    part1_with_rules(input, &Rules::standard())
+}
+
+#[aoc(day2, part2)]
+pub fn part2(input: &str) -> OutputType {
+    part2_with_rules(input, &Rules::standard())
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -199,6 +481,17 @@ B X
 C Z"
     }
 
+    fn get_rpsls_rules() -> &'static str {
+        "# Rock Paper Scissors Lizard Spock
+shapes = Rock Paper Scissors Lizard Spock
+shape_scores = 1 2 3 4 5
+outcome_scores = 0 3 6
+beats = Scissors>Paper Paper>Rock Rock>Lizard Lizard>Spock Spock>Scissors Scissors>Lizard Lizard>Paper Paper>Spock Spock>Rock Rock>Scissors
+opponent = A:Rock B:Paper C:Scissors D:Lizard E:Spock
+response = V:Rock W:Paper X:Scissors Y:Lizard Z:Spock
+result = V:Lose W:Lose X:Draw Y:Win Z:Win"
+    }
+
     #[test]
     fn day2_part1() {
         assert_eq!(part1(get_test_input()), 15);
@@ -208,4 +501,86 @@ C Z"
     fn day2_part2() {
         assert_eq!(part2(get_test_input()), 12);
     }
+
+    #[test]
+    fn day2_rpsls() {
+        let rules = Rules::from_config(get_rpsls_rules()).unwrap();
+        //Spock vs Rock win, Lizard vs Spock win, Scissors vs Scissors draw
+        assert_eq!(part1_with_rules("A Z\nE Y\nC X", &rules), 11 + 10 + 6);
+        //Beat Rock: Paper(2) or Spock(5), take Spock. Lose to Lizard: Paper(2) or Spock(5),
+        //take Spock. Draw with Paper
+        assert_eq!(part2_with_rules("A Y\nD V\nB X", &rules), 11 + 5 + 5);
+
+        //Same game as the standard one, just with different scoring, still the same code path
+        let rescored = STANDARD_RULES
+            .replace("shape_scores = 1 2 3", "shape_scores = 10 20 30")
+            .replace("outcome_scores = 0 3 6", "outcome_scores = 1 2 3");
+        let rules = Rules::from_config(&rescored).unwrap();
+        assert_eq!(part1_with_rules(get_test_input(), &rules), 23 + 11 + 32);
+    }
+
+    #[test]
+    fn day2_analysis() {
+        let rules = Rules::standard();
+        let analysis = analyse_responses(get_test_input(), &rules);
+        assert_eq!(analysis.opponent, vec![1.0 / 3.0; 3]);
+        //Rock averages 4, Paper 5, Scissors 6
+        assert_eq!(analysis.best_fixed.0, Hand(2));
+        assert!((analysis.best_fixed.1 - 6.0).abs() < 1e-9);
+        assert_eq!(
+            analysis.best_per_move,
+            vec![
+                (Hand(0), Hand(1), 8),
+                (Hand(1), Hand(2), 9),
+                (Hand(2), Hand(0), 7)
+            ]
+        );
+
+        //Always Scissors: each round is 3, 6 or 9 with equal odds, so 18 +- 18 over three rounds
+        let mc = monte_carlo(get_test_input(), &rules, &[0.0, 0.0, 1.0], 20000, 2022);
+        assert!((mc.mean - 18.0).abs() < 0.2, "{:?}", mc);
+        assert!((mc.variance - 18.0).abs() < 1.0, "{:?}", mc);
+
+        //Mixed strategy should land on the analytical expectation
+        let strategy = [0.2, 0.3, 0.5];
+        let expected = 3.0 * expected_round_score(&rules, &analysis.opponent, &strategy);
+        let mc = monte_carlo(get_test_input(), &rules, &strategy, 20000, 7);
+        assert!((mc.mean - expected).abs() < 0.2, "{:?} vs {}", mc, expected);
+    }
+
+    #[test]
+    fn day2_bad_rules() {
+        //Rock and Scissors never meet
+        assert!(
+            Rules::from_config(&STANDARD_RULES.replace("beats = Rock>Scissors ", "beats = "))
+                .is_err()
+        );
+        //Both beat each other
+        assert!(Rules::from_config(
+            &STANDARD_RULES.replace("Scissors>Paper", "Scissors>Paper Scissors>Rock")
+        )
+        .is_err());
+        assert!(Rules::from_config(&STANDARD_RULES.replace("Z:Scissors", "Z:Lizard")).is_err());
+        assert!(Rules::from_config("shapes = Rock").is_err());
+
+        //A tournament, but Rock can't lose and Paper can't win, so part 2 would have no answer
+        let err = Rules::from_config(&STANDARD_RULES.replace(
+            "beats = Rock>Scissors Paper>Rock Scissors>Paper",
+            "beats = Rock>Scissors Rock>Paper Scissors>Paper",
+        ))
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Nothing beats Rock");
+        //A single shape can only ever draw
+        let err = Rules::from_config(
+            "shapes = Rock
+shape_scores = 1
+outcome_scores = 0 3 6
+beats =
+opponent = A:Rock
+response = X:Rock
+result = X:Lose Y:Draw Z:Win",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("two shapes"), "{}", err);
+    }
 }
@@ -0,0 +1,445 @@
+use std::collections::{HashMap, VecDeque};
+
+type InputType = (Board, Vec<Step>);
+type OutputType = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Void,
+    Open,
+    Wall,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Forward(usize),
+    Left,
+    Right,
+}
+
+// Facing is encoded the same way the puzzle scores it: 0 = right, 1 = down, 2 = left, 3 = up
+type Facing = usize;
+type Coordinate = (usize, usize);
+
+const DELTAS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+pub struct Board {
+    grid: Vec<Vec<Tile>>,
+    width: usize,
+    height: usize,
+}
+
+impl Board {
+    fn new(input: &str) -> Self {
+        let mut grid = input
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| match c {
+                        ' ' => Tile::Void,
+                        '.' => Tile::Open,
+                        '#' => Tile::Wall,
+                        _ => panic!("Unknown tile {}", c),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        //Lines are ragged (trailing spaces are not in the input), pad them all out so the rest of
+        //the code can just index without caring
+        let width = grid.iter().map(|row| row.len()).max().unwrap();
+        let height = grid.len();
+        for row in grid.iter_mut() {
+            row.resize(width, Tile::Void);
+        }
+
+        Board {
+            grid,
+            width,
+            height,
+        }
+    }
+
+    fn get(&self, (x, y): Coordinate) -> Tile {
+        self.grid[y][x]
+    }
+
+    fn in_map(&self, x: i64, y: i64) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.get((x as usize, y as usize)) != Tile::Void
+    }
+
+    fn start(&self) -> Coordinate {
+        let x = self.grid[0].iter().position(|t| *t == Tile::Open).unwrap();
+        (x, 0)
+    }
+
+    fn tile_count(&self) -> usize {
+        self.grid
+            .iter()
+            .map(|row| row.iter().filter(|t| **t != Tile::Void).count())
+            .sum()
+    }
+}
+
+fn parse_path(line: &str) -> Vec<Step> {
+    let mut steps = vec![];
+    let mut num = 0;
+    let mut in_num = false;
+    for c in line.trim().chars() {
+        if let Some(d) = c.to_digit(10) {
+            num = num * 10 + d as usize;
+            in_num = true;
+            continue;
+        }
+        if in_num {
+            steps.push(Step::Forward(num));
+            num = 0;
+            in_num = false;
+        }
+        steps.push(match c {
+            'L' => Step::Left,
+            'R' => Step::Right,
+            _ => panic!("Unknown step {}", c),
+        });
+    }
+    if in_num {
+        steps.push(Step::Forward(num));
+    }
+    steps
+}
+
+#[aoc_generator(day22)]
+fn day22_parse(input: &str) -> InputType {
+    let (map, path) = input.split_once("\n\n").unwrap();
+    (Board::new(map), parse_path(path))
+}
+
+// Decides where you end up when stepping off the edge of the map (or off a face of the cube).
+pub trait Wrap {
+    fn wrap(&self, board: &Board, pos: Coordinate, facing: Facing) -> (Coordinate, Facing);
+}
+
+// Part 1: come back in on the opposite side of the same row or column.
+pub struct FlatWrap;
+
+impl Wrap for FlatWrap {
+    fn wrap(&self, board: &Board, (x, y): Coordinate, facing: Facing) -> (Coordinate, Facing) {
+        //Walk backwards until we fall off the other side, the last tile we were on is where we
+        //come back in
+        let (dx, dy) = DELTAS[(facing + 2) % 4];
+        let (mut x, mut y) = (x as i64, y as i64);
+        while board.in_map(x + dx, y + dy) {
+            x += dx;
+            y += dy;
+        }
+        ((x as usize, y as usize), facing)
+    }
+}
+
+type Vec3 = [i64; 3];
+
+fn neg(v: Vec3) -> Vec3 {
+    [-v[0], -v[1], -v[2]]
+}
+
+fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(v: Vec3, s: i64) -> Vec3 {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+fn dot(a: Vec3, b: Vec3) -> i64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+// Where a face of the net ended up once folded: which way its outward normal points, and which
+// way its local "right" (+x) and "down" (+y) axes point in 3D.
+#[derive(Debug, Clone, Copy)]
+struct Face {
+    origin: Coordinate,
+    normal: Vec3,
+    right: Vec3,
+    down: Vec3,
+}
+
+impl Face {
+    fn facing_vector(&self, facing: Facing) -> Vec3 {
+        match facing {
+            0 => self.right,
+            1 => self.down,
+            2 => neg(self.right),
+            3 => neg(self.down),
+            _ => unreachable!(),
+        }
+    }
+
+    fn facing_from_vector(&self, v: Vec3) -> Facing {
+        (0..4).find(|f| self.facing_vector(*f) == v).unwrap()
+    }
+}
+
+// Part 2: fold the net up into a cube and walk across the edges.
+//
+// Nothing here knows the shape of the net. We BFS over the faces of the net, rolling the cube as
+// we go so every face learns its orientation in 3D, then wrapping is just "step off this face onto
+// the face whose normal points the way we were walking".
+//
+// Tiles are placed on a cube spanning [-N, N] on every axis (N = face size), at doubled
+// coordinates, so every tile centre is a unique integer point.
+pub struct CubeWrap {
+    size: usize,
+    faces: Vec<Face>,
+    face_lookup: HashMap<Coordinate, usize>,
+}
+
+impl CubeWrap {
+    pub fn new(board: &Board) -> Self {
+        let tiles = board.tile_count();
+        assert_eq!(tiles % 6, 0, "A cube net needs 6 equal faces");
+        let size = (1..).find(|n| n * n >= tiles / 6).unwrap();
+        assert_eq!(size * size * 6, tiles, "Faces are not square");
+
+        let mut face_lookup = HashMap::new();
+        let mut faces: Vec<Face> = vec![];
+
+        let start = board.start();
+        let start = (start.0 / size, start.1 / size);
+
+        let mut to_examine = VecDeque::new();
+        face_lookup.insert(start, 0);
+        faces.push(Face {
+            origin: (start.0 * size, start.1 * size),
+            normal: [0, 0, -1],
+            right: [1, 0, 0],
+            down: [0, 1, 0],
+        });
+        to_examine.push_back(start);
+
+        while let Some((fx, fy)) = to_examine.pop_front() {
+            let face = faces[face_lookup[&(fx, fy)]];
+            for (facing, (dx, dy)) in DELTAS.iter().enumerate() {
+                let (nx, ny) = (fx as i64 + dx, fy as i64 + dy);
+                if !board.in_map(nx * size as i64, ny * size as i64) {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                if face_lookup.contains_key(&(nx, ny)) {
+                    continue;
+                }
+                //Roll the cube over the shared edge; whichever axis we moved along becomes the new
+                //normal, and the old normal now points back the way we came
+                let (normal, right, down) = match facing {
+                    0 => (face.right, neg(face.normal), face.down),
+                    1 => (face.down, face.right, neg(face.normal)),
+                    2 => (neg(face.right), face.normal, face.down),
+                    3 => (neg(face.down), face.right, face.normal),
+                    _ => unreachable!(),
+                };
+                face_lookup.insert((nx, ny), faces.len());
+                faces.push(Face {
+                    origin: (nx * size, ny * size),
+                    normal,
+                    right,
+                    down,
+                });
+                to_examine.push_back((nx, ny));
+            }
+        }
+
+        assert_eq!(faces.len(), 6, "Net is not connected");
+        for (i, a) in faces.iter().enumerate() {
+            for b in faces[i + 1..].iter() {
+                assert_ne!(a.normal, b.normal, "Net folds two faces onto each other");
+            }
+        }
+
+        CubeWrap {
+            size,
+            faces,
+            face_lookup,
+        }
+    }
+
+    fn face_at(&self, (x, y): Coordinate) -> &Face {
+        &self.faces[self.face_lookup[&(x / self.size, y / self.size)]]
+    }
+
+    fn board_to_cube(&self, (x, y): Coordinate) -> Vec3 {
+        let n = self.size as i64;
+        let face = self.face_at((x, y));
+        let i = (x - face.origin.0) as i64;
+        let j = (y - face.origin.1) as i64;
+        add(
+            scale(face.normal, n),
+            add(
+                scale(face.right, 2 * i + 1 - n),
+                scale(face.down, 2 * j + 1 - n),
+            ),
+        )
+    }
+
+    fn cube_to_board(&self, face: &Face, p: Vec3) -> Coordinate {
+        let n = self.size as i64;
+        let i = (dot(p, face.right) + n - 1) / 2;
+        let j = (dot(p, face.down) + n - 1) / 2;
+        (face.origin.0 + i as usize, face.origin.1 + j as usize)
+    }
+}
+
+impl Wrap for CubeWrap {
+    fn wrap(&self, _board: &Board, pos: Coordinate, facing: Facing) -> (Coordinate, Facing) {
+        let face = self.face_at(pos);
+        let heading = face.facing_vector(facing);
+        let next_face = self.faces.iter().find(|f| f.normal == heading).unwrap();
+
+        //Going over the edge moves us one (doubled) unit further along our heading and one unit in
+        //from the old face, and we're now walking "into" the cube from the old face's side
+        let p = add(self.board_to_cube(pos), add(heading, neg(face.normal)));
+        let next_facing = next_face.facing_from_vector(neg(face.normal));
+
+        (self.cube_to_board(next_face, p), next_facing)
+    }
+}
+
+fn step(
+    board: &Board,
+    wrap: &dyn Wrap,
+    (x, y): Coordinate,
+    facing: Facing,
+) -> (Coordinate, Facing) {
+    let (dx, dy) = DELTAS[facing];
+    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+    if board.in_map(nx, ny) {
+        ((nx as usize, ny as usize), facing)
+    } else {
+        wrap.wrap(board, (x, y), facing)
+    }
+}
+
+fn trace_path(board: &Board, path: &[Step], wrap: &dyn Wrap) -> (Coordinate, Facing) {
+    let mut pos = board.start();
+    let mut facing = 0;
+
+    for s in path {
+        match s {
+            Step::Left => facing = (facing + 3) % 4,
+            Step::Right => facing = (facing + 1) % 4,
+            Step::Forward(n) => {
+                for _ in 0..*n {
+                    let (next_pos, next_facing) = step(board, wrap, pos, facing);
+                    if board.get(next_pos) == Tile::Wall {
+                        break;
+                    }
+                    pos = next_pos;
+                    facing = next_facing;
+                }
+            }
+        }
+    }
+
+    (pos, facing)
+}
+
+fn password(((x, y), facing): (Coordinate, Facing)) -> OutputType {
+    1000 * (y + 1) + 4 * (x + 1) + facing
+}
+
+#[aoc(day22, part1)]
+pub fn part1((board, path): &InputType) -> OutputType {
+    password(trace_path(board, path, &FlatWrap))
+}
+
+#[aoc(day22, part2)]
+pub fn part2((board, path): &InputType) -> OutputType {
+    password(trace_path(board, path, &CubeWrap::new(board)))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn get_test_input() -> &'static str {
+        "        ...#
+        .#..
+        #...
+        ....
+...#.......#
+........#...
+..#....#....
+..........#.
+        ...#....
+        .....#..
+        .#......
+        ......#.
+
+10R5L5R10L4R5L5"
+    }
+
+    //Build an all open net out of a layout of faces ('#' = face) with the given face size
+    fn open_net(layout: &str, size: usize) -> Board {
+        let map = layout
+            .lines()
+            .flat_map(|line| {
+                let row = line
+                    .chars()
+                    .map(|c| {
+                        if c == '#' {
+                            ".".repeat(size)
+                        } else {
+                            " ".repeat(size)
+                        }
+                    })
+                    .collect::<String>();
+                std::iter::repeat_n(row, size)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Board::new(&map)
+    }
+
+    #[test]
+    fn day22_part1() {
+        assert_eq!(part1(&day22_parse(get_test_input())), 6032);
+    }
+
+    #[test]
+    fn day22_part2() {
+        assert_eq!(part2(&day22_parse(get_test_input())), 5031);
+    }
+
+    #[test]
+    fn day22_cube_wrap_any_net() {
+        //The example net, the usual real input net, and a couple of others; walking 4N tiles in a
+        //straight line on an open cube has to bring you right back where you started
+        for (layout, size) in [
+            ("  #\n###\n  ##", 4),
+            (" ##\n #\n##\n#", 50),
+            (" #\n###\n #\n #", 3),
+            ("#\n##\n ##\n  #", 5),
+        ] {
+            let board = open_net(layout, size);
+            let cube = CubeWrap::new(&board);
+            for y in 0..board.height {
+                for x in 0..board.width {
+                    if board.get((x, y)) == Tile::Void {
+                        continue;
+                    }
+                    for facing in 0..4 {
+                        let (mut pos, mut f) = ((x, y), facing);
+                        for _ in 0..4 * size {
+                            (pos, f) = step(&board, &cube, pos, f);
+                        }
+                        assert_eq!((pos, f), ((x, y), facing), "{:?}", layout);
+                    }
+                }
+            }
+        }
+    }
+}
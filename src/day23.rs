@@ -0,0 +1,238 @@
+type InputType = Grove;
+type OutputType = usize;
+
+type Coordinate = (i32, i32);
+
+// How much room to leave around the elves whenever the board has to grow; the elves spread out
+// about one tile per round at most, so this keeps re-allocations rare
+static GROWTH_MARGIN: i32 = 16;
+
+// N, S, W, E, each with the three tiles that have to be empty before an elf will propose it. The
+// first of the three is the tile actually moved to
+const PROPOSALS: [[Coordinate; 3]; 4] = [
+    [(0, -1), (-1, -1), (1, -1)],
+    [(0, 1), (-1, 1), (1, 1)],
+    [(-1, 0), (-1, -1), (-1, 1)],
+    [(1, 0), (1, -1), (1, 1)],
+];
+
+const NEIGHBORS: [Coordinate; 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Same idea as the day14 HashMap of spaces, but a HashMap was far too slow for the ~1000 rounds of
+// part 2. Instead keep a dense occupancy grid with an offset so coordinates can go negative, and
+// re-allocate it bigger whenever an elf wanders close to the edge. The elves themselves are kept
+// in a Vec so we never have to scan the empty space.
+#[derive(Clone)]
+pub struct Grove {
+    elves: Vec<Coordinate>,
+    min_x: i32,
+    min_y: i32,
+    width: usize,
+    height: usize,
+    occupied: Vec<bool>,
+}
+
+impl Grove {
+    fn new(elves: Vec<Coordinate>) -> Self {
+        let mut grove = Grove {
+            elves,
+            min_x: 0,
+            min_y: 0,
+            width: 0,
+            height: 0,
+            occupied: vec![],
+        };
+        grove.regrow();
+        grove
+    }
+
+    fn index(&self, (x, y): Coordinate) -> Option<usize> {
+        let (x, y) = (x - self.min_x, y - self.min_y);
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            None
+        } else {
+            Some(y as usize * self.width + x as usize)
+        }
+    }
+
+    fn contains(&self, pos: Coordinate) -> bool {
+        self.index(pos).map(|i| self.occupied[i]).unwrap_or(false)
+    }
+
+    fn bounds(&self) -> (Coordinate, Coordinate) {
+        let min_x = self.elves.iter().map(|e| e.0).min().unwrap();
+        let max_x = self.elves.iter().map(|e| e.0).max().unwrap();
+        let min_y = self.elves.iter().map(|e| e.1).min().unwrap();
+        let max_y = self.elves.iter().map(|e| e.1).max().unwrap();
+        ((min_x, min_y), (max_x, max_y))
+    }
+
+    fn regrow(&mut self) {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        self.min_x = min_x - GROWTH_MARGIN;
+        self.min_y = min_y - GROWTH_MARGIN;
+        self.width = (max_x - min_x + 1 + 2 * GROWTH_MARGIN) as usize;
+        self.height = (max_y - min_y + 1 + 2 * GROWTH_MARGIN) as usize;
+        self.occupied = vec![false; self.width * self.height];
+        for i in 0..self.elves.len() {
+            let idx = self.index(self.elves[i]).unwrap();
+            self.occupied[idx] = true;
+        }
+    }
+
+    fn near_edge(&self, (x, y): Coordinate) -> bool {
+        x - 1 <= self.min_x
+            || y - 1 <= self.min_y
+            || x + 1 >= self.min_x + self.width as i32 - 1
+            || y + 1 >= self.min_y + self.height as i32 - 1
+    }
+
+    // Returns true if any elf moved. `round` is zero-based and picks which direction is considered
+    // first
+    fn round(&mut self, round: usize, targets: &mut Vec<u8>) -> bool {
+        if self.elves.iter().any(|e| self.near_edge(*e)) {
+            self.regrow();
+        }
+
+        let proposals = self
+            .elves
+            .iter()
+            .map(|&(x, y)| {
+                if NEIGHBORS
+                    .iter()
+                    .all(|(dx, dy)| !self.contains((x + dx, y + dy)))
+                {
+                    return None;
+                }
+                (0..4)
+                    .map(|d| &PROPOSALS[(round + d) % 4])
+                    .find(|checks| {
+                        checks
+                            .iter()
+                            .all(|(dx, dy)| !self.contains((x + dx, y + dy)))
+                    })
+                    .map(|checks| (x + checks[0].0, y + checks[0].1))
+            })
+            .collect::<Vec<_>>();
+
+        //Count how many elves want each tile, reusing the buffer between rounds
+        targets.clear();
+        targets.resize(self.occupied.len(), 0);
+        for target in proposals.iter().flatten() {
+            let idx = self.index(*target).unwrap();
+            targets[idx] = targets[idx].saturating_add(1);
+        }
+
+        let mut moved = false;
+        for (elf, proposal) in self.elves.iter_mut().zip(proposals) {
+            if let Some(target) = proposal {
+                let (x, y) = (target.0 - self.min_x, target.1 - self.min_y);
+                let to = y as usize * self.width + x as usize;
+                if targets[to] == 1 {
+                    let (x, y) = (elf.0 - self.min_x, elf.1 - self.min_y);
+                    self.occupied[y as usize * self.width + x as usize] = false;
+                    self.occupied[to] = true;
+                    *elf = target;
+                    moved = true;
+                }
+            }
+        }
+        moved
+    }
+
+    fn empty_ground(&self) -> usize {
+        let ((min_x, min_y), (max_x, max_y)) = self.bounds();
+        ((max_x - min_x + 1) * (max_y - min_y + 1)) as usize - self.elves.len()
+    }
+}
+
+#[aoc_generator(day23)]
+fn day23_parse(input: &str) -> InputType {
+    let elves = input
+        .lines()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+        .collect::<Vec<_>>();
+    Grove::new(elves)
+}
+
+#[aoc(day23, part1)]
+pub fn part1(input: &InputType) -> OutputType {
+    let mut grove = input.clone();
+    let mut targets = vec![];
+    for round in 0..10 {
+        grove.round(round, &mut targets);
+    }
+    grove.empty_ground()
+}
+
+#[aoc(day23, part2)]
+pub fn part2(input: &InputType) -> OutputType {
+    let mut grove = input.clone();
+    let mut targets = vec![];
+    (0..)
+        .find(|round| !grove.round(*round, &mut targets))
+        .unwrap()
+        + 1
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn get_test_input() -> &'static str {
+        "....#..
+..###.#
+#...#.#
+.#...##
+#.###..
+##.#.##
+.#..#.."
+    }
+
+    #[test]
+    fn day23_part1() {
+        assert_eq!(part1(&day23_parse(get_test_input())), 110);
+    }
+
+    #[test]
+    fn day23_part2() {
+        assert_eq!(part2(&day23_parse(get_test_input())), 20);
+    }
+
+    #[test]
+    fn day23_small_example() {
+        //The five elf example, it settles after three rounds with this layout
+        let mut grove = day23_parse(
+            ".....
+..##.
+..#..
+.....
+..##.
+.....",
+        );
+        let mut targets = vec![];
+        for round in 0..3 {
+            assert!(grove.round(round, &mut targets));
+        }
+        assert!(!grove.round(3, &mut targets));
+        let mut elves = grove.elves.clone();
+        elves.sort();
+        assert_eq!(elves, vec![(0, 2), (2, 0), (2, 5), (4, 1), (4, 3)]);
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{HashSet, VecDeque};
+
+type InputType = Valley;
+type OutputType = usize;
+
+// Interior coordinates, (0, 0) is the first open tile inside the walls. The entrance and exit sit
+// just outside the interior, at y = -1 and y = height
+type Coordinate = (i32, i32);
+
+// Rather than simulating every blizzard every minute, remember where each one started. A blizzard
+// only ever moves along its own row or column and wraps around, so "is there a blizzard on (x, y)
+// at minute t" is just "did a right-moving one start at (x - t) mod width in this row" (and the
+// same for the other three directions). Everything repeats every lcm(width, height) minutes.
+pub struct Valley {
+    width: usize,
+    height: usize,
+    // Indexed [y][x] for the horizontal ones and [x][y] for the vertical ones
+    right: Vec<Vec<bool>>,
+    left: Vec<Vec<bool>>,
+    down: Vec<Vec<bool>>,
+    up: Vec<Vec<bool>>,
+    start: Coordinate,
+    end: Coordinate,
+}
+
+impl Valley {
+    fn occupied(&self, (x, y): Coordinate, time: usize) -> bool {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (x, y, t) = (x as i64, y as i64, time as i64);
+        self.right[y as usize][(x - t).rem_euclid(w) as usize]
+            || self.left[y as usize][(x + t).rem_euclid(w) as usize]
+            || self.down[x as usize][(y - t).rem_euclid(h) as usize]
+            || self.up[x as usize][(y + t).rem_euclid(h) as usize]
+    }
+
+    fn open(&self, (x, y): Coordinate, time: usize) -> bool {
+        if (x, y) == self.start || (x, y) == self.end {
+            return true;
+        }
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        !self.occupied((x, y), time)
+    }
+
+    fn period(&self) -> usize {
+        let gcd = |mut a: usize, mut b: usize| {
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            a
+        };
+        self.width * self.height / gcd(self.width, self.height)
+    }
+}
+
+#[aoc_generator(day24)]
+fn day24_parse(input: &str) -> InputType {
+    let lines = input.lines().collect::<Vec<_>>();
+    //Strip off the walls
+    let height = lines.len() - 2;
+    let width = lines[0].len() - 2;
+
+    let mut right = vec![vec![false; width]; height];
+    let mut left = vec![vec![false; width]; height];
+    let mut down = vec![vec![false; height]; width];
+    let mut up = vec![vec![false; height]; width];
+
+    for (y, line) in lines[1..=height].iter().enumerate() {
+        for (x, c) in line.chars().skip(1).take(width).enumerate() {
+            match c {
+                '>' => right[y][x] = true,
+                '<' => left[y][x] = true,
+                'v' => down[x][y] = true,
+                '^' => up[x][y] = true,
+                '.' => (),
+                _ => panic!("Unknown tile {}", c),
+            }
+        }
+    }
+
+    let gap = |line: &str| line.chars().position(|c| c == '.').unwrap() as i32 - 1;
+
+    Valley {
+        width,
+        height,
+        right,
+        left,
+        down,
+        up,
+        start: (gap(lines[0]), -1),
+        end: (gap(lines[height + 1]), height as i32),
+    }
+}
+
+// Same BFS as day12, but every state also carries the time (modulo the blizzard period) since the
+// same tile can be safe at one minute and deadly the next. Waiting in place is just another move
+pub fn cross(valley: &Valley, from: Coordinate, to: Coordinate, start_time: usize) -> OutputType {
+    let period = valley.period();
+    let mut visited: HashSet<(Coordinate, usize)> = HashSet::new();
+    let mut to_examine: VecDeque<(Coordinate, usize)> = VecDeque::new();
+
+    to_examine.push_back((from, start_time));
+    visited.insert((from, start_time % period));
+
+    while let Some(((x, y), time)) = to_examine.pop_front() {
+        if (x, y) == to {
+            return time;
+        }
+        for (dx, dy) in [(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let next = (x + dx, y + dy);
+            if !valley.open(next, time + 1) {
+                continue;
+            }
+            if visited.insert((next, (time + 1) % period)) {
+                to_examine.push_back((next, time + 1));
+            }
+        }
+    }
+    panic!("No way across the valley")
+}
+
+#[aoc(day24, part1)]
+pub fn part1(input: &InputType) -> OutputType {
+    cross(input, input.start, input.end, 0)
+}
+
+#[aoc(day24, part2)]
+pub fn part2(input: &InputType) -> OutputType {
+    let there = cross(input, input.start, input.end, 0);
+    let back = cross(input, input.end, input.start, there);
+    cross(input, input.start, input.end, back)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn get_test_input() -> &'static str {
+        "#.######
+#>>.<^<#
+#.<..<<#
+#>v.><>#
+#<^v^^>#
+######.#"
+    }
+
+    #[test]
+    fn day24_part1() {
+        assert_eq!(part1(&day24_parse(get_test_input())), 18);
+    }
+
+    #[test]
+    fn day24_part2() {
+        assert_eq!(part2(&day24_parse(get_test_input())), 54);
+    }
+
+    #[test]
+    fn day24_blizzard_period() {
+        //The simple example from the puzzle, one blizzard each way; after lcm(5, 5) minutes
+        //everything is back where it started
+        let valley = day24_parse(
+            "#.#####
+#.....#
+#>....#
+#.....#
+#...v.#
+#.....#
+#####.#",
+        );
+        assert_eq!(valley.period(), 5);
+        assert!(valley.occupied((0, 1), 0));
+        assert!(valley.occupied((3, 1), 3));
+        assert!(valley.occupied((3, 4), 1));
+        assert!(valley.occupied((3, 0), 2));
+        assert!(!valley.occupied((0, 1), 1));
+        for t in 0..5 {
+            for y in 0..5 {
+                for x in 0..5 {
+                    assert_eq!(valley.occupied((x, y), t), valley.occupied((x, y), t + 5));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+extern crate anyhow;
+use self::anyhow::{anyhow, Error};
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+use std::str::FromStr;
+
+type InputType = Vec<Snafu>;
+type OutputType = String;
+
+// Balanced base 5: each digit is one of 2, 1, 0, - (-1) or = (-2). Just keep the plain integer
+// around and only deal with the digits when parsing and printing, then all the arithmetic is free
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Snafu(i128);
+
+impl Snafu {
+    pub fn checked_add(self, other: Snafu) -> Option<Snafu> {
+        self.0.checked_add(other.0).map(Snafu)
+    }
+}
+
+impl FromStr for Snafu {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(anyhow!("Empty SNAFU number"));
+        }
+        let mut val: i128 = 0;
+        for c in s.chars() {
+            let digit: i128 = match c {
+                '2' => 2,
+                '1' => 1,
+                '0' => 0,
+                '-' => -1,
+                '=' => -2,
+                _ => return Err(anyhow!("Bad SNAFU digit {:?} in {:?}", c, s)),
+            };
+            //When val and digit pull in opposite directions val * 5 can overflow even though the
+            //result fits (i128::MIN ends in a 2), so move a 5 from the digit over to val first
+            let (v, d) = match (val.signum(), digit.signum()) {
+                (-1, 1) => (val + 1, digit - 5),
+                (1, -1) => (val - 1, digit + 5),
+                _ => (val, digit),
+            };
+            val = v
+                .checked_mul(5)
+                .and_then(|v| v.checked_add(d))
+                .ok_or_else(|| anyhow!("SNAFU number {:?} is too large", s))?;
+        }
+        Ok(Snafu(val))
+    }
+}
+
+impl fmt::Display for Snafu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0 == 0 {
+            return write!(f, "0");
+        }
+        let mut digits = vec![];
+        let mut n = self.0;
+        while n != 0 {
+            //Pick the remainder in -2..=2 rather than 0..5, and carry the difference
+            //(Divide first, n - rem can overflow at the ends of the range)
+            let mut rem = n.rem_euclid(5);
+            n = n.div_euclid(5);
+            if rem > 2 {
+                rem -= 5;
+                n += 1;
+            }
+            digits.push(match rem {
+                2 => '2',
+                1 => '1',
+                0 => '0',
+                -1 => '-',
+                -2 => '=',
+                _ => unreachable!(),
+            });
+        }
+        write!(f, "{}", digits.iter().rev().collect::<String>())
+    }
+}
+
+impl From<i64> for Snafu {
+    fn from(n: i64) -> Self {
+        Snafu(n as i128)
+    }
+}
+
+impl From<i128> for Snafu {
+    fn from(n: i128) -> Self {
+        Snafu(n)
+    }
+}
+
+impl From<Snafu> for i128 {
+    fn from(n: Snafu) -> Self {
+        n.0
+    }
+}
+
+impl TryFrom<Snafu> for i64 {
+    type Error = Error;
+
+    fn try_from(n: Snafu) -> Result<Self, Self::Error> {
+        i64::try_from(n.0).map_err(|_| anyhow!("{} does not fit in an i64", n))
+    }
+}
+
+impl Add for Snafu {
+    type Output = Snafu;
+
+    // Same in debug and release: overflowing is a bug, not something to quietly wrap. Use
+    // checked_add where that can happen
+    fn add(self, other: Snafu) -> Snafu {
+        self.checked_add(other)
+            .unwrap_or_else(|| panic!("SNAFU overflow adding {} and {}", self, other))
+    }
+}
+
+impl AddAssign for Snafu {
+    fn add_assign(&mut self, other: Snafu) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Snafu {
+    fn sum<I: Iterator<Item = Snafu>>(iter: I) -> Self {
+        iter.fold(Snafu::default(), |acc, n| acc + n)
+    }
+}
+
+impl<'a> Sum<&'a Snafu> for Snafu {
+    fn sum<I: Iterator<Item = &'a Snafu>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+#[aoc_generator(day25)]
+fn day25_parse(input: &str) -> InputType {
+    input
+        .lines()
+        .map(|line| line.trim().parse::<Snafu>().unwrap())
+        .collect()
+}
+
+#[aoc(day25, part1)]
+pub fn part1(input: &InputType) -> OutputType {
+    input.iter().sum::<Snafu>().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn get_test_input() -> &'static str {
+        "1=-0-2
+12111
+2=0=
+21
+2=01
+111
+20012
+112
+1=-1=
+1-12
+12
+1=
+122"
+    }
+
+    #[test]
+    fn day25_part1() {
+        assert_eq!(part1(&day25_parse(get_test_input())), "2=-1=0");
+    }
+
+    #[test]
+    fn day25_snafu_round_trip() {
+        for (n, s) in [
+            (1i64, "1"),
+            (2, "2"),
+            (3, "1="),
+            (4, "1-"),
+            (5, "10"),
+            (8, "2="),
+            (10, "20"),
+            (15, "1=0"),
+            (20, "1-0"),
+            (2022, "1=11-2"),
+            (12345, "1-0---0"),
+            (314159265, "1121-1110-1=0"),
+        ] {
+            assert_eq!(Snafu::from(n).to_string(), s);
+            assert_eq!(i64::try_from(s.parse::<Snafu>().unwrap()).unwrap(), n);
+        }
+
+        for n in -2000i64..2000 {
+            let s = Snafu::from(n);
+            assert_eq!(s.to_string().parse::<Snafu>().unwrap(), s);
+            assert_eq!(i64::try_from(s).unwrap(), n);
+            assert_eq!(s + Snafu::from(n * 7), Snafu::from(n * 8));
+        }
+
+        let big = Snafu::from(i128::MAX / 3);
+        assert_eq!(
+            i128::from(big.to_string().parse::<Snafu>().unwrap()),
+            i128::MAX / 3
+        );
+        assert!(i64::try_from(big).is_err());
+        for n in [i128::MIN, i128::MAX] {
+            let s = Snafu::from(n).to_string();
+            assert_eq!(i128::from(s.parse::<Snafu>().unwrap()), n);
+        }
+        assert!("12a".parse::<Snafu>().is_err());
+        assert!("".parse::<Snafu>().is_err());
+
+        //Adding right up to the edge is fine, one past it isn't
+        let max = Snafu::from(i128::MAX);
+        let halves = [Snafu::from(i128::MAX / 2), Snafu::from(i128::MAX / 2 + 1)];
+        assert_eq!(halves.iter().sum::<Snafu>(), max);
+        assert_eq!(
+            max.checked_add(Snafu::from(-1i64)),
+            Some(Snafu::from(i128::MAX - 1))
+        );
+        assert_eq!(max.checked_add(Snafu::from(1i64)), None);
+        assert!(std::panic::catch_unwind(|| max + Snafu::from(1i64)).is_err());
+    }
+}
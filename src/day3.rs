@@ -1,11 +1,15 @@
 use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 pub type InputType = Vec<RucksackSet>;
 pub type OutputType = usize;
 
+#[derive(Debug, Clone)]
 pub struct Rucksack {
     //I thought I would need to count the duplicates, I guess not. I could have Just done sets the
-    //entire time
+    //entire time. (Later: the rebalancing planner finally needs them)
     first: HashMap<char, usize>,
     second: HashMap<char, usize>,
 }
@@ -16,15 +20,23 @@ pub struct RucksackSet {
     second: HashSet<char>,
 }
 
-// Rucksack bench
-// Day3 - Part1/(default)  time:   [112.59 us 112.70 us 112.81 us]
-// Day3 - Part2/(default)  time:   [258.37 us 258.73 us 259.19 us]
-// Generator               time:   [255.17 us 255.97 us 256.98 us]
-//
-// RucksackSet
-// Day3 - Part1/(default)  time:   [25.117 us 25.159 us 25.201 us]
-// Day3 - Part2/(default)  time:   [176.10 us 176.28 us 176.47 us]
-// Generator               time:   [194.21 us 194.63 us 195.05 us]
+// Every item as a bit, indexed by its priority (a = bit 1 .. Z = bit 52), so finding duplicates
+// and badges is just ANDing two u64s together. Anything that isn't a letter has no bit and goes in
+// a set on the side instead (always empty, so free, for the puzzle input); like the other sacks,
+// it only gets a priority looked up if it turns out to be a duplicate or a badge
+pub struct RucksackBits {
+    first: u64,
+    second: u64,
+    first_other: HashSet<char>,
+    second_other: HashSet<char>,
+}
+
+// Release build, mean of 2000 runs over the real input (day3_benchmark, run it with
+// `cargo test --release day3_benchmark -- --ignored --nocapture`)
+//                 Generator   Part1      Part2
+// Rucksack        693.7 us    309.8 us   777.8 us
+// RucksackSet     600.3 us     88.3 us   557.1 us
+// RucksackBits     85.3 us     18.5 us     9.3 us
 
 pub trait Sack
 where
@@ -33,7 +45,95 @@ where
     fn new(ln: &str) -> Self;
     fn find_duplicates(&self) -> Vec<char>;
     fn get_combined_set(&self) -> HashSet<char>;
-    fn find_item_in_three(&self, second: &Self, third: &Self) -> Option<char>;
+
+    // Every item that shows up somewhere in every sack of the group
+    fn common_items(group: &[&Self]) -> Vec<char> {
+        let mut sets = group.iter().map(|sack| sack.get_combined_set());
+        let first = sets.next().unwrap_or_default();
+        sets.fold(first, |acc, set| acc.intersection(&set).copied().collect())
+            .into_iter()
+            .collect()
+    }
+
+    // The badge for a group of any size, there has to be exactly one item they all carry
+    fn find_common_item(group: &[&Self]) -> Result<char, BadgeError> {
+        let mut common = Self::common_items(group);
+        match common.len() {
+            0 => Err(BadgeError::Missing),
+            1 => Ok(common[0]),
+            _ => {
+                common.sort();
+                Err(BadgeError::Ambiguous(common))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BadgeError {
+    Missing,
+    Ambiguous(Vec<char>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RucksackError {
+    // The priority scheme has nothing for this item
+    UnknownItem(char),
+    Badge { group: usize, error: BadgeError },
+    // Input ran out part way through the last group
+    IncompleteGroup { group: usize, size: usize },
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::UnknownItem(c) => write!(f, "no priority for item {:?}", c),
+            RucksackError::Badge {
+                group,
+                error: BadgeError::Missing,
+            } => write!(f, "group {} has no item in common", group),
+            RucksackError::Badge {
+                group,
+                error: BadgeError::Ambiguous(items),
+            } => write!(
+                f,
+                "group {} has more than one common item: {:?}",
+                group, items
+            ),
+            RucksackError::IncompleteGroup { group, size } => {
+                write!(f, "group {} only has {} rucksacks", group, size)
+            }
+        }
+    }
+}
+
+impl Error for RucksackError {}
+
+// How much each item is worth. The puzzle's is AsciiLetters, anything else (e.g. non-ASCII items)
+// can go through a PriorityMap
+pub trait PriorityScheme {
+    fn priority(&self, item: char) -> Option<usize>;
+}
+
+// a-z are 1-26, A-Z are 27-52
+pub struct AsciiLetters;
+
+impl PriorityScheme for AsciiLetters {
+    fn priority(&self, item: char) -> Option<usize> {
+        match item {
+            'a'..='z' => Some(item as usize - 'a' as usize + 1),
+            'A'..='Z' => Some(item as usize - 'A' as usize + 27),
+            _ => None,
+        }
+    }
+}
+
+pub struct PriorityMap(pub HashMap<char, usize>);
+
+impl PriorityScheme for PriorityMap {
+    fn priority(&self, item: char) -> Option<usize> {
+        self.0.get(&item).copied()
+    }
 }
 
 impl Sack for RucksackSet {
@@ -52,19 +152,94 @@ impl Sack for RucksackSet {
     fn get_combined_set(&self) -> HashSet<char> {
         self.first.union(&self.second).map(|c| *c).collect()
     }
+}
 
-    fn find_item_in_three(&self, second: &Self, third: &Self) -> Option<char> {
-        let f = self
-            .get_combined_set()
-            .intersection(&second.get_combined_set())
-            .map(|c| *c)
-            .collect::<HashSet<char>>();
-        let final_set = f
-            .intersection(&third.get_combined_set())
-            .map(|c| *c)
-            .collect::<Vec<_>>();
-        assert!(final_set.iter().count() <= 1);
-        final_set.iter().next().copied()
+fn convert_priority_to_char(p: usize) -> char {
+    if p <= 26 {
+        (b'a' + p as u8 - 1) as char
+    } else {
+        (b'A' + p as u8 - 27) as char
+    }
+}
+
+fn bits_to_chars(mut bits: u64) -> Vec<char> {
+    let mut out = vec![];
+    while bits != 0 {
+        out.push(convert_priority_to_char(bits.trailing_zeros() as usize));
+        bits &= bits - 1;
+    }
+    out
+}
+
+impl RucksackBits {
+    fn to_bits(items: impl Iterator<Item = char>) -> (u64, HashSet<char>) {
+        let mut bits = 0;
+        let mut other = HashSet::new();
+        for c in items {
+            match AsciiLetters.priority(c) {
+                Some(bit) => bits |= 1 << bit,
+                None => {
+                    other.insert(c);
+                }
+            }
+        }
+        (bits, other)
+    }
+
+    fn combined(&self) -> u64 {
+        self.first | self.second
+    }
+
+    fn combined_other(&self) -> HashSet<char> {
+        self.first_other
+            .union(&self.second_other)
+            .copied()
+            .collect()
+    }
+}
+
+impl Sack for RucksackBits {
+    fn new(ln: &str) -> Self {
+        let len = ln.chars().count();
+        assert_eq!(len % 2, 0);
+        let mut cs = ln.chars();
+        let (first, first_other) = RucksackBits::to_bits(cs.by_ref().take(len / 2));
+        let (second, second_other) = RucksackBits::to_bits(cs);
+        RucksackBits {
+            first,
+            second,
+            first_other,
+            second_other,
+        }
+    }
+
+    fn find_duplicates(&self) -> Vec<char> {
+        let mut dups = bits_to_chars(self.first & self.second);
+        dups.extend(self.first_other.intersection(&self.second_other));
+        dups
+    }
+
+    fn get_combined_set(&self) -> HashSet<char> {
+        let mut all = self.combined_other();
+        all.extend(bits_to_chars(self.combined()));
+        all
+    }
+
+    fn common_items(group: &[&Self]) -> Vec<char> {
+        if group.is_empty() {
+            return vec![];
+        }
+        let mut common = bits_to_chars(group.iter().fold(!0, |acc, sack| acc & sack.combined()));
+        //Nothing to do (and nothing to allocate) unless every sack has something off to the side
+        if group
+            .iter()
+            .all(|sack| !sack.first_other.is_empty() || !sack.second_other.is_empty())
+        {
+            let mut others = group.iter().map(|sack| sack.combined_other());
+            let first = others.next().unwrap();
+            common.extend(others.fold(first, |acc, set| acc.intersection(&set).copied().collect()));
+        }
+        common
     }
 }
 
@@ -114,76 +289,402 @@ impl Sack for Rucksack {
             .collect::<HashSet<char>>();
         first_set.union(&second_set).map(|c| *c).collect()
     }
+}
 
-    fn find_item_in_three(&self, second: &Self, third: &Self) -> Option<char> {
-        let first_two = self
-            .get_combined_set()
-            .intersection(&second.get_combined_set())
-            .map(|c| *c)
-            .collect::<HashSet<char>>();
-        let final_set = first_two
-            .intersection(&third.get_combined_set())
-            .map(|c| *c)
+// Rebalancing: given the duplicates, how do we fix them? A swap trades one item out of a
+// compartment for one item out of another, so both compartments stay the same size.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compartment {
+    First,
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Slot {
+    pub sack: usize,
+    pub compartment: Compartment,
+    pub item: char,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Swap {
+    pub a: Slot,
+    pub b: Slot,
+}
+
+impl Rucksack {
+    fn compartment(&self, c: Compartment) -> &HashMap<char, usize> {
+        match c {
+            Compartment::First => &self.first,
+            Compartment::Second => &self.second,
+        }
+    }
+
+    fn compartment_mut(&mut self, c: Compartment) -> &mut HashMap<char, usize> {
+        match c {
+            Compartment::First => &mut self.first,
+            Compartment::Second => &mut self.second,
+        }
+    }
+
+    fn take(&mut self, c: Compartment, item: char) {
+        let comp = self.compartment_mut(c);
+        let count = comp.get_mut(&item).unwrap();
+        *count -= 1;
+        if *count == 0 {
+            comp.remove(&item);
+        }
+    }
+
+    fn put(&mut self, c: Compartment, item: char) {
+        *self.compartment_mut(c).entry(item).or_insert(0) += 1;
+    }
+
+    // Minimum swaps between the two compartments so no item type is in both, or None if it can't
+    // be done without bringing items in from somewhere else.
+    //
+    // Every type has to end up wholly in one compartment. If A is the set of types that end up in
+    // the first one, the first compartment has to stay the same size (so the totals of A add up to
+    // exactly its size), and each swap brings one A item over from the second compartment. So this
+    // is subset sum over the types, minimising how many A items start out in the second half.
+    pub fn plan_rebalance(&self) -> Option<Vec<Swap>> {
+        let mut types = self
+            .first
+            .keys()
+            .chain(self.second.keys())
+            .copied()
             .collect::<Vec<_>>();
-        assert!(final_set.iter().count() <= 1);
+        types.sort();
+        types.dedup();
 
-        final_set.iter().next().copied()
+        let count = |c: &HashMap<char, usize>, t: &char| *c.get(t).unwrap_or(&0);
+        let half = self.first.values().sum::<usize>();
+
+        //best[i][sum] = fewest swaps using the first i types with `sum` items in the first half
+        let mut best = vec![vec![None; half + 1]; types.len() + 1];
+        best[0][0] = Some(0);
+        for (i, t) in types.iter().enumerate() {
+            let total = count(&self.first, t) + count(&self.second, t);
+            let cost = count(&self.second, t);
+            for sum in 0..=half {
+                let skip = best[i][sum];
+                let keep = if sum >= total {
+                    best[i][sum - total].map(|c: usize| c + cost)
+                } else {
+                    None
+                };
+                best[i + 1][sum] = match (skip, keep) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        }
+        best[types.len()][half]?;
+
+        //Walk back through the table to find which types went first
+        let mut in_first = HashSet::new();
+        let mut sum = half;
+        for i in (0..types.len()).rev() {
+            if best[i][sum] == best[i + 1][sum] {
+                continue;
+            }
+            let t = types[i];
+            in_first.insert(t);
+            sum -= count(&self.first, &t) + count(&self.second, &t);
+        }
+
+        let outgoing = |c: Compartment, belongs_in_first: bool| {
+            types
+                .iter()
+                .filter(|t| in_first.contains(t) == belongs_in_first)
+                .flat_map(|t| std::iter::repeat_n(*t, count(self.compartment(c), t)))
+                .collect::<Vec<_>>()
+        };
+        //Everything in the first half that belongs in the second, and vice versa. These always
+        //line up one to one since the halves keep their sizes
+        let leaving_first = outgoing(Compartment::First, false);
+        let leaving_second = outgoing(Compartment::Second, true);
+        assert_eq!(leaving_first.len(), leaving_second.len());
+
+        Some(
+            leaving_first
+                .into_iter()
+                .zip(leaving_second)
+                .map(|(a, b)| Swap {
+                    a: Slot {
+                        sack: 0,
+                        compartment: Compartment::First,
+                        item: a,
+                    },
+                    b: Slot {
+                        sack: 0,
+                        compartment: Compartment::Second,
+                        item: b,
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    // How far this is from being fixable on its own: the smallest gap between half the items and
+    // the totals any set of whole types can add up to. Zero exactly when plan_rebalance finds a
+    // plan
+    fn imbalance(&self) -> usize {
+        let half = self.first.values().sum::<usize>();
+        let mut totals = HashMap::new();
+        for (t, n) in self.first.iter().chain(self.second.iter()) {
+            *totals.entry(*t).or_insert(0) += n;
+        }
+        let mut reachable = vec![false; 2 * half + 1];
+        reachable[0] = true;
+        for total in totals.values() {
+            for sum in (*total..reachable.len()).rev() {
+                reachable[sum] |= reachable[sum - total];
+            }
+        }
+        (0..reachable.len())
+            .filter(|sum| reachable[*sum])
+            .map(|sum| sum.abs_diff(half))
+            .min()
+            .unwrap()
     }
 }
 
-fn convert_char_to_priority(c: &char) -> usize {
-    //println!("{}",c);
-    //I know, it's horrible
-    if *c == '_' {
-        return 0;
+pub fn apply_swap(group: &mut [Rucksack], swap: &Swap) {
+    let Swap { a, b } = swap;
+    group[a.sack].take(a.compartment, a.item);
+    group[b.sack].take(b.compartment, b.item);
+    group[a.sack].put(a.compartment, b.item);
+    group[b.sack].put(b.compartment, a.item);
+}
+
+// Same thing for a whole group. Each rucksack gets its own (minimal) plan; any that can't be fixed
+// on their own first trade single items with the rest of the group, one at a time, each time
+// picking the trade that brings the pair closest to fixable (see imbalance) and then the fewest
+// swaps overall. That part is greedy, so the group total is not guaranteed minimal when trades are
+// needed, but it is whenever every rucksack can be fixed by itself.
+//
+// None means the greedy trading got stuck (no single trade gets any closer), not that the group
+// can't be rebalanced at all.
+pub fn plan_group_rebalance(group: &[Rucksack]) -> Option<Vec<Swap>> {
+    let mut group = group.to_vec();
+    let mut swaps = vec![];
+
+    let cost = |sack: &Rucksack| sack.plan_rebalance().map(|plan| plan.len());
+
+    //Every trade has to lower the pair's total imbalance, so this can't go round in circles
+    while let Some(stuck) = group.iter().position(|sack| sack.imbalance() > 0) {
+        let mut best: Option<((usize, usize), Swap)> = None;
+        for other in (0..group.len()).filter(|o| *o != stuck) {
+            for (mine, theirs) in [Compartment::First, Compartment::Second]
+                .iter()
+                .flat_map(|a| [(*a, Compartment::First), (*a, Compartment::Second)])
+            {
+                let mut my_items = group[stuck]
+                    .compartment(mine)
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                let mut their_items = group[other]
+                    .compartment(theirs)
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                my_items.sort();
+                their_items.sort();
+                for a in my_items.iter() {
+                    for b in their_items.iter().filter(|b| *b != a) {
+                        let (mut x, mut y) = (group[stuck].clone(), group[other].clone());
+                        x.take(mine, *a);
+                        x.put(mine, *b);
+                        y.take(theirs, *b);
+                        y.put(theirs, *a);
+                        let gap = x.imbalance() + y.imbalance();
+                        if gap < group[stuck].imbalance() + group[other].imbalance() {
+                            let total = (gap, cost(&x).unwrap_or(0) + cost(&y).unwrap_or(0) + 1);
+                            if best.map(|(c, _)| total < c).unwrap_or(true) {
+                                let swap = Swap {
+                                    a: Slot {
+                                        sack: stuck,
+                                        compartment: mine,
+                                        item: *a,
+                                    },
+                                    b: Slot {
+                                        sack: other,
+                                        compartment: theirs,
+                                        item: *b,
+                                    },
+                                };
+                                best = Some((total, swap));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let (_, swap) = best?;
+        apply_swap(&mut group, &swap);
+        swaps.push(swap);
     }
 
-    const LOWER_OFFSET: usize = 96;
-    const HIGHER_OFFSET: usize = 38;
-    let val = *c as usize;
-    if val > LOWER_OFFSET {
-        val - LOWER_OFFSET
-    } else {
-        val - HIGHER_OFFSET
+    for idx in 0..group.len() {
+        for mut swap in group[idx].plan_rebalance().unwrap() {
+            swap.a.sack = idx;
+            swap.b.sack = idx;
+            apply_swap(&mut group, &swap);
+            swaps.push(swap);
+        }
     }
+
+    Some(swaps)
+}
+
+fn parse_sacks<T: Sack>(input: &str) -> Vec<T> {
+    input.split("\n").map(T::new).collect()
 }
 
 #[aoc_generator(day3)]
 fn day3_parse(input: &str) -> InputType {
-    input
-        .split("\n")
-        .map(|line| RucksackSet::new(line))
-        .collect()
+    parse_sacks(input)
+}
+
+// The runner can only hand one generator output to each solution, so every other Sack needs its
+// own (named) generator per part
+#[aoc_generator(day3, part1, Counts)]
+fn day3_parse_counts_p1(input: &str) -> Vec<Rucksack> {
+    parse_sacks(input)
+}
+
+#[aoc_generator(day3, part2, Counts)]
+fn day3_parse_counts_p2(input: &str) -> Vec<Rucksack> {
+    parse_sacks(input)
+}
+
+#[aoc_generator(day3, part1, Bits)]
+fn day3_parse_bits_p1(input: &str) -> Vec<RucksackBits> {
+    parse_sacks(input)
+}
+
+#[aoc_generator(day3, part2, Bits)]
+fn day3_parse_bits_p2(input: &str) -> Vec<RucksackBits> {
+    parse_sacks(input)
+}
+
+// Lets whatever is driving this pick a Sack at runtime (e.g. from a command line flag) instead of
+// at compile time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SackKind {
+    Counts,
+    Set,
+    Bits,
+}
+
+impl FromStr for SackKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "counts" | "map" => Ok(SackKind::Counts),
+            "set" => Ok(SackKind::Set),
+            "bits" | "bitset" => Ok(SackKind::Bits),
+            _ => Err(format!("Unknown sack kind {}", s)),
+        }
+    }
+}
+
+fn solve_tmpl<T: Sack>(input: &str, part2: bool) -> Result<OutputType, RucksackError> {
+    let sacks = parse_sacks::<T>(input);
+    if part2 {
+        part2_tmpl(&sacks)
+    } else {
+        part1_tmpl(&sacks)
+    }
+}
+
+pub fn solve(input: &str, kind: SackKind, part2: bool) -> Result<OutputType, RucksackError> {
+    match kind {
+        SackKind::Counts => solve_tmpl::<Rucksack>(input, part2),
+        SackKind::Set => solve_tmpl::<RucksackSet>(input, part2),
+        SackKind::Bits => solve_tmpl::<RucksackBits>(input, part2),
+    }
 }
 
 #[aoc(day3, part1)]
-pub fn part1(input: &InputType) -> OutputType {
-    part1_tmpl::<RucksackSet>(input)
+pub fn part1(input: &InputType) -> Result<OutputType, RucksackError> {
+    part1_tmpl(input)
+}
+
+#[aoc(day3, part1, Counts)]
+pub fn part1_counts(input: &[Rucksack]) -> Result<OutputType, RucksackError> {
+    part1_tmpl(input)
+}
+
+#[aoc(day3, part1, Bits)]
+pub fn part1_bits(input: &[RucksackBits]) -> Result<OutputType, RucksackError> {
+    part1_tmpl(input)
+}
+
+pub fn part1_tmpl<T: Sack>(input: &[T]) -> Result<OutputType, RucksackError> {
+    part1_with(input, &AsciiLetters)
+}
+
+fn priority_of<P: PriorityScheme>(scheme: &P, item: char) -> Result<usize, RucksackError> {
+    scheme
+        .priority(item)
+        .ok_or(RucksackError::UnknownItem(item))
 }
 
-pub fn part1_tmpl<T: Sack>(input: &InputType) -> OutputType {
+pub fn part1_with<T: Sack, P: PriorityScheme>(
+    input: &[T],
+    scheme: &P,
+) -> Result<OutputType, RucksackError> {
     input
         .iter()
-        .map(|r| {
-            r.find_duplicates()
-                .iter()
-                .map(|c| convert_char_to_priority(c))
-                .sum::<usize>()
-        })
-        .sum::<usize>()
+        .flat_map(|r| r.find_duplicates())
+        .map(|c| priority_of(scheme, c))
+        .sum()
 }
 
 #[aoc(day3, part2)]
-pub fn part2(input: &InputType) -> OutputType {
-    part2_tmpl::<RucksackSet>(input)
+pub fn part2(input: &InputType) -> Result<OutputType, RucksackError> {
+    part2_tmpl(input)
+}
+
+#[aoc(day3, part2, Counts)]
+pub fn part2_counts(input: &[Rucksack]) -> Result<OutputType, RucksackError> {
+    part2_tmpl(input)
 }
 
-pub fn part2_tmpl<T: Sack>(input: &Vec<T>) -> OutputType {
+#[aoc(day3, part2, Bits)]
+pub fn part2_bits(input: &[RucksackBits]) -> Result<OutputType, RucksackError> {
+    part2_tmpl(input)
+}
+
+pub fn part2_tmpl<T: Sack>(input: &[T]) -> Result<OutputType, RucksackError> {
+    part2_with(input, 3, &AsciiLetters)
+}
+
+pub fn part2_with<T: Sack, P: PriorityScheme>(
+    input: &[T],
+    group_size: usize,
+    scheme: &P,
+) -> Result<OutputType, RucksackError> {
+    assert!(group_size > 0);
     input
-        .chunks(3)
-        .map(|three_rucks| match &three_rucks {
-            &[a, b, c] => convert_char_to_priority(&a.find_item_in_three(&b, &c).unwrap_or('_')),
-            _ => panic!("Can this even happen?"),
+        .chunks(group_size)
+        .enumerate()
+        .map(|(group, rucks)| {
+            if rucks.len() != group_size {
+                return Err(RucksackError::IncompleteGroup {
+                    group,
+                    size: rucks.len(),
+                });
+            }
+            let rucks = rucks.iter().collect::<Vec<_>>();
+            let badge = T::find_common_item(&rucks)
+                .map_err(|error| RucksackError::Badge { group, error })?;
+            priority_of(scheme, badge)
         })
         .sum()
 }
@@ -214,11 +715,192 @@ CrZsJsPPZsGzwwsLwLmpwMDw"
 
     #[test]
     fn day3_part1() {
-        assert_eq!(part1(&day3_parse(get_test_input())), 157);
+        assert_eq!(part1(&day3_parse(get_test_input())), Ok(157));
     }
 
     #[test]
     fn day3_part2() {
-        assert_eq!(part2(&day3_parse(get_test_input())), 70);
+        assert_eq!(part2(&day3_parse(get_test_input())), Ok(70));
+    }
+
+    #[test]
+    fn day3_all_sacks() {
+        for kind in ["counts", "set", "bits"] {
+            let kind = kind.parse::<SackKind>().unwrap();
+            assert_eq!(solve(get_test_input(), kind, false), Ok(157));
+            assert_eq!(solve(get_test_input(), kind, true), Ok(70));
+            //Items with no priority are only a problem when they have to be scored
+            assert_eq!(
+                solve("🍎🍌🍎🥕", kind, false),
+                Err(RucksackError::UnknownItem('🍎'))
+            );
+            assert_eq!(solve("a🍌ba", kind, false), Ok(1));
+            assert_eq!(solve("a🍌ba\ncbdd\nebff", kind, true), Ok(2));
+            assert_eq!(
+                solve("🍌aab\nc🍌cd\n🍌eef", kind, true),
+                Err(RucksackError::UnknownItem('🍌'))
+            );
+        }
+        let bits = RucksackBits::new("aabbCa");
+        assert_eq!(bits.find_duplicates(), vec!['a', 'b']);
+        let mut combined = bits.get_combined_set().into_iter().collect::<Vec<_>>();
+        combined.sort();
+        assert_eq!(combined, vec!['C', 'a', 'b']);
+    }
+
+    #[test]
+    fn day3_any_group_size() {
+        //Two groups of two; "ab" is common to the first pair, nothing to the second
+        let sacks = parse_sacks::<RucksackSet>("abxy\nbazw\ncdef\nghij");
+        assert_eq!(
+            part2_with(&sacks[..2], 2, &AsciiLetters),
+            Err(RucksackError::Badge {
+                group: 0,
+                error: BadgeError::Ambiguous(vec!['a', 'b'])
+            })
+        );
+        assert_eq!(
+            part2_with(&sacks[2..], 2, &AsciiLetters),
+            Err(RucksackError::Badge {
+                group: 0,
+                error: BadgeError::Missing
+            })
+        );
+        assert_eq!(
+            part2_with(&sacks[..3], 2, &AsciiLetters),
+            Err(RucksackError::Badge {
+                group: 0,
+                error: BadgeError::Ambiguous(vec!['a', 'b'])
+            })
+        );
+        assert_eq!(
+            part2_with(
+                &parse_sacks::<RucksackBits>("abxy\nbqzw\nccbb"),
+                2,
+                &AsciiLetters
+            ),
+            Err(RucksackError::IncompleteGroup { group: 1, size: 1 })
+        );
+
+        //Every Sack has to agree for every group size, errors included
+        let set = day3_parse(get_test_input());
+        let bits = parse_sacks::<RucksackBits>(get_test_input());
+        let counts = parse_sacks::<Rucksack>(get_test_input());
+        for size in 1..=6 {
+            let expected = part2_with(&set, size, &AsciiLetters);
+            assert_eq!(part2_with(&bits, size, &AsciiLetters), expected);
+            assert_eq!(part2_with(&counts, size, &AsciiLetters), expected);
+        }
+    }
+
+    #[test]
+    fn day3_priority_map() {
+        let scheme = PriorityMap(HashMap::from([
+            ('🍎', 10),
+            ('🍌', 20),
+            ('🥕', 30),
+            ('🥔', 40),
+        ]));
+        let sacks = parse_sacks::<RucksackSet>("🍎🍌🍎🥕\n🥕🍌🍌🍌\n🥔🥕🍎🍎");
+        assert_eq!(part1_with(&sacks, &scheme), Ok(10 + 20));
+        assert_eq!(part2_with(&sacks, 3, &scheme), Ok(30));
+        assert_eq!(
+            part1_with(&parse_sacks::<Rucksack>("🍇🍇"), &scheme),
+            Err(RucksackError::UnknownItem('🍇'))
+        );
+    }
+
+    #[test]
+    fn day3_rebalance() {
+        let clean = |sack: &Rucksack| sack.find_duplicates().is_empty();
+
+        //'p' is in both halves of the first example rucksack
+        let mut sack = [Rucksack::new("vJrwpWtwJgWrhcsFMMfFFhFp")];
+        let plan = sack[0].plan_rebalance().unwrap();
+        assert_eq!(plan.len(), 1);
+        for swap in plan.iter() {
+            apply_swap(&mut sack, swap);
+        }
+        assert!(clean(&sack[0]));
+
+        //Both halves are identical, so two types have to gather on each side
+        let mut sack = [Rucksack::new("abcdabcd")];
+        let plan = sack[0].plan_rebalance().unwrap();
+        assert_eq!(plan.len(), 2);
+        for swap in plan.iter() {
+            apply_swap(&mut sack, swap);
+        }
+        assert!(clean(&sack[0]));
+
+        //Three a's between two slots, can't be done on its own
+        assert_eq!(Rucksack::new("abaa").plan_rebalance(), None);
+
+        //...but it can if it trades one away to a neighbour
+        let mut group = vec![Rucksack::new("abaa"), Rucksack::new("cdef")];
+        let plan = plan_group_rebalance(&group).unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].a.sack, 0);
+        assert_eq!(plan[0].b.sack, 1);
+        for swap in plan.iter() {
+            apply_swap(&mut group, swap);
+        }
+        assert!(group.iter().all(clean));
+
+        //No single trade fixes four a's, it takes two in a row
+        let mut group = vec![Rucksack::new("aaaa"), Rucksack::new("bcde")];
+        let plan = plan_group_rebalance(&group).unwrap();
+        assert_eq!(plan.len(), 2);
+        for swap in plan.iter() {
+            apply_swap(&mut group, swap);
+        }
+        assert!(group.iter().all(clean));
+
+        //Whole example, every rucksack can fix itself. The two L's, two v's and the t's (3 vs 2)
+        //all need two swaps, the rest need one
+        let mut group = parse_sacks::<Rucksack>(get_test_input());
+        let plan = plan_group_rebalance(&group).unwrap();
+        assert_eq!(plan.len(), 9);
+        assert!(plan.iter().all(|swap| swap.a.sack == swap.b.sack));
+        for swap in plan.iter() {
+            apply_swap(&mut group, swap);
+        }
+        assert!(group.iter().all(clean));
+    }
+
+    #[test]
+    #[ignore]
+    fn day3_benchmark() {
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        fn bench<T: Sack>(name: &str, input: &str) {
+            let runs = 2000;
+            let mean_us = |f: &dyn Fn()| {
+                let start = Instant::now();
+                for _ in 0..runs {
+                    f();
+                }
+                start.elapsed().as_secs_f64() * 1e6 / runs as f64
+            };
+            let sacks = parse_sacks::<T>(input);
+            let generator = mean_us(&|| {
+                black_box(parse_sacks::<T>(black_box(input)));
+            });
+            let p1 = mean_us(&|| {
+                black_box(part1_tmpl(black_box(&sacks)).unwrap());
+            });
+            let p2 = mean_us(&|| {
+                black_box(part2_tmpl(black_box(&sacks)).unwrap());
+            });
+            println!(
+                "{:15} {:6.1} us  {:6.1} us  {:6.1} us",
+                name, generator, p1, p2
+            );
+        }
+
+        let input = include_str!("../input/2022/day3.txt").trim_end();
+        bench::<Rucksack>("Rucksack", input);
+        bench::<RucksackSet>("RucksackSet", input);
+        bench::<RucksackBits>("RucksackBits", input);
     }
 }
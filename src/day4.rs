@@ -3,12 +3,16 @@ extern crate lazy_static;
 use self::lazy_static::lazy_static;
 extern crate regex;
 use self::regex::Regex;
+extern crate anyhow;
+use self::anyhow::{anyhow, Result};
+
+use std::fmt;
+
+use interval::{Interval, IntervalSet};
 
 pub struct ElfPair {
-    elf_1_lower: u64,
-    elf_1_higher: u64,
-    elf_2_lower: u64,
-    elf_2_higher: u64,
+    elf_1: Interval,
+    elf_2: Interval,
 }
 
 type InputType = Vec<ElfPair>;
@@ -38,11 +42,18 @@ impl ElfPair {
             .unwrap();
         let elf_2_higher = chars.collect::<String>().parse::<u64>().unwrap();
 
+        ElfPair::from_bounds(elf_1_lower, elf_1_higher, elf_2_lower, elf_2_higher)
+    }
+
+    fn from_bounds(
+        elf_1_lower: u64,
+        elf_1_higher: u64,
+        elf_2_lower: u64,
+        elf_2_higher: u64,
+    ) -> Self {
         ElfPair {
-            elf_1_lower,
-            elf_1_higher,
-            elf_2_lower,
-            elf_2_higher,
+            elf_1: Interval::new(elf_1_lower as i64, elf_1_higher as i64),
+            elf_2: Interval::new(elf_2_lower as i64, elf_2_higher as i64),
         }
     }
 
@@ -58,59 +69,317 @@ impl ElfPair {
         //We already pulled them out with a
         //numeric regex, we can assume they
         //are correct
-        ElfPair {
-            elf_1_lower: caps[1].parse::<u64>().unwrap(),
-            elf_1_higher: caps[2].parse::<u64>().unwrap(),
-            elf_2_lower: caps[3].parse::<u64>().unwrap(),
-            elf_2_higher: caps[4].parse::<u64>().unwrap(),
-        }
+        ElfPair::from_bounds(
+            caps[1].parse::<u64>().unwrap(),
+            caps[2].parse::<u64>().unwrap(),
+            caps[3].parse::<u64>().unwrap(),
+            caps[4].parse::<u64>().unwrap(),
+        )
     }
 
     //395 too low
     fn one_in_pair_is_subset(&self) -> bool {
-        assert!(self.elf_1_lower <= self.elf_1_higher);
-        assert!(self.elf_2_lower <= self.elf_2_higher);
-
-        if self.elf_1_lower < self.elf_2_lower {
-            //The first elf is "lower" the second must be within it
-            // [X1             X2]
-            //    [Y1 ...      Y2]
-            if self.elf_1_lower <= self.elf_2_higher && self.elf_1_higher >= self.elf_2_higher {
-                return true;
-            }
-        } else if self.elf_1_lower == self.elf_2_lower {
-            //If they're both the same start, then either one is certainly a subset of the other
-            return true;
+        self.elf_1.contains_interval(&self.elf_2) || self.elf_2.contains_interval(&self.elf_1)
+    }
+
+    fn any_overlap(&self) -> bool {
+        self.elf_1.overlaps(&self.elf_2)
+    }
+}
+
+// Any number of elves on one line, "2-4,6-8,3-5". The pair above is kept as is for the puzzle,
+// this is the planning view: who overlaps whom, what nobody covers, what is covered too often and
+// which elves could be sent home without losing any sections
+pub struct ElfGroup {
+    pub elves: Vec<Interval>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Disjoint,
+    Overlaps,
+    Contains,
+    ContainedBy,
+    Equal,
+}
+
+impl Relation {
+    fn between(a: &Interval, b: &Interval) -> Self {
+        if a == b {
+            Relation::Equal
+        } else if a.contains_interval(b) {
+            Relation::Contains
+        } else if b.contains_interval(a) {
+            Relation::ContainedBy
+        } else if a.overlaps(b) {
+            Relation::Overlaps
         } else {
-            // elf 2 is the lower bound
-            if self.elf_2_lower <= self.elf_1_higher && self.elf_2_higher >= self.elf_1_higher {
-                return true;
+            Relation::Disjoint
+        }
+    }
+}
+
+impl From<&ElfPair> for ElfGroup {
+    fn from(pair: &ElfPair) -> Self {
+        ElfGroup {
+            elves: vec![pair.elf_1, pair.elf_2],
+        }
+    }
+}
+
+impl ElfGroup {
+    pub fn new(line: &str) -> Result<Self> {
+        let elves = line
+            .split(',')
+            .map(|range| {
+                let (lower, higher) = range
+                    .trim()
+                    .split_once('-')
+                    .ok_or(anyhow!("Range {:?} is missing a '-'", range))?;
+                let lower = lower.parse::<i64>()?;
+                let higher = higher.parse::<i64>()?;
+                if lower > higher {
+                    return Err(anyhow!("Range {:?} runs backwards", range));
+                }
+                Ok(Interval::new(lower, higher))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ElfGroup { elves })
+    }
+
+    // matrix[i][j] is how elf i's range relates to elf j's
+    pub fn relation_matrix(&self) -> Vec<Vec<Relation>> {
+        self.elves
+            .iter()
+            .map(|a| self.elves.iter().map(|b| Relation::between(a, b)).collect())
+            .collect()
+    }
+
+    pub fn covered(&self) -> IntervalSet {
+        self.elves.iter().copied().collect()
+    }
+
+    // Sections inside `within` that nobody is assigned to
+    pub fn uncovered(&self, within: Interval) -> IntervalSet {
+        IntervalSet::from(within).difference(&self.covered())
+    }
+
+    // Sections that more than k elves are assigned to, k = 1 being "any overlap at all"
+    pub fn covered_by_more_than(&self, k: usize) -> IntervalSet {
+        //Sweep from left to right, +1 where a range starts and -1 just after it ends
+        let mut events = self
+            .elves
+            .iter()
+            .flat_map(|iv| [(iv.start, 1), (iv.end + 1, -1)])
+            .collect::<Vec<(i64, i64)>>();
+        events.sort();
+
+        let mut out = IntervalSet::new();
+        let mut count = 0;
+        let mut crowded_since = None;
+        for (pos, delta) in events {
+            count += delta;
+            match crowded_since {
+                None if count > k as i64 => crowded_since = Some(pos),
+                Some(start) if count <= k as i64 => {
+                    out.insert(Interval::new(start, pos - 1));
+                    crowded_since = None;
+                }
+                _ => (),
             }
         }
-        //println!("{}-{},{}-{}",self.elf_1_lower, self.elf_1_higher, self.elf_2_lower, self.elf_2_higher);
-        return false;
+        out
     }
 
-    fn any_overlap(&self) -> bool {
-        if self.elf_1_lower < self.elf_2_lower {
-            // If x_1 is lower than y_1, then it's a subset as long as x_1 does not begin before
-            // y_1
-            if self.elf_1_higher >= self.elf_2_lower {
-                return true;
+    // Indices of the fewest elves that still cover every section any elf covers. Each merged run
+    // is covered greedily: from the first uncovered section, take whoever starts at or before it
+    // and reaches furthest. Classic interval cover, and the greedy choice is optimal.
+    pub fn minimal_cover(&self) -> Vec<usize> {
+        let mut order = (0..self.elves.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| self.elves[i].start);
+
+        let mut chosen = vec![];
+        let mut next = 0;
+        for run in self.covered().iter() {
+            let mut needed_from = run.start;
+            while needed_from <= run.end {
+                let mut best: Option<usize> = None;
+                while next < order.len() && self.elves[order[next]].start <= needed_from {
+                    let candidate = order[next];
+                    if best.is_none_or(|b| self.elves[candidate].end > self.elves[b].end) {
+                        best = Some(candidate);
+                    }
+                    next += 1;
+                }
+                //The runs are the union of the ranges, so somebody always starts in time
+                let best = best.expect("Gap inside a covered run");
+                chosen.push(best);
+                needed_from = self.elves[best].end + 1;
             }
-        } else if self.elf_1_lower == self.elf_2_lower {
-            //If they start at the same place, they of course, MUST overlap
-            //I don't check if the higher is the same, because all the other comparisions are on
-            //the lower bound, and would get captured by the other checks
-            return true;
-        } else {
-            //Same here but the opposite
-            if self.elf_2_higher >= self.elf_1_lower {
-                return true;
+        }
+        chosen.sort();
+        chosen
+    }
+}
+
+// A proposed fix for a group: everyone keeps a contiguous range (or is sent home), nobody
+// overlaps, and the same sections as before are still covered
+pub struct Reassignment {
+    pub old: Vec<Interval>,
+    pub new: Vec<Option<Interval>>,
+}
+
+impl Reassignment {
+    // How many elves got a different range
+    pub fn changed(&self) -> usize {
+        self.old
+            .iter()
+            .zip(self.new.iter())
+            .filter(|(old, new)| Some(**old) != **new)
+            .count()
+    }
+
+    // Sections that stayed with the elf who had them, what reassign maximises
+    pub fn kept(&self) -> u64 {
+        self.old
+            .iter()
+            .zip(self.new.iter())
+            .filter_map(|(old, new)| new.and_then(|new| old.intersection(&new)))
+            .map(|both| both.size())
+            .sum()
+    }
+
+    // Checked the same way part2 counts overlaps, every two elves that still have work must not
+    // be an overlapping pair
+    pub fn verify(&self) -> Result<()> {
+        let assigned = self.new.iter().flatten().collect::<Vec<_>>();
+        for (i, a) in assigned.iter().enumerate() {
+            for b in assigned[i + 1..].iter() {
+                let pair = ElfPair {
+                    elf_1: **a,
+                    elf_2: **b,
+                };
+                if pair.any_overlap() {
+                    return Err(anyhow!("{:?} and {:?} still overlap", a, b));
+                }
+            }
+        }
+        let before = self.old.iter().copied().collect::<IntervalSet>();
+        let after = assigned.into_iter().copied().collect::<IntervalSet>();
+        if before != after {
+            return Err(anyhow!("Covers {:?} instead of {:?}", after, before));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Reassignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |iv: &Interval| format!("{}-{}", iv.start, iv.end);
+        for (elf, (old, new)) in self.old.iter().zip(self.new.iter()).enumerate() {
+            match new {
+                Some(new) if new == old => writeln!(f, "  elf {}: {}", elf, show(old))?,
+                Some(new) => {
+                    writeln!(f, "- elf {}: {}", elf, show(old))?;
+                    writeln!(f, "+ elf {}: {}", elf, show(new))?;
+                }
+                None => {
+                    writeln!(f, "- elf {}: {}", elf, show(old))?;
+                    writeln!(f, "+ elf {}: (none)", elf)?;
+                }
             }
         }
-        return false;
+        Ok(())
+    }
+}
+
+// Hand out the covered sections again so that nobody overlaps and nobody is over their
+// max_lengths[elf], changing as little as possible. "As little as possible" is measured in
+// sections: keep the most sections each elf already had, which is the same as the fewest
+// sections gained plus lost since the total covered doesn't change.
+//
+// Elves keep their left to right order (by where their old range starts), so walking the covered
+// sections in order, each elf in turn either takes the next run of sections or nothing at all.
+// That makes it a DP over (elves handled, sections covered), exact under that ordering.
+// It's per section, so fine for puzzle sized ranges, not for ranges in the billions.
+pub fn reassign(group: &ElfGroup, max_lengths: &[u64]) -> Result<Reassignment> {
+    if max_lengths.len() != group.elves.len() {
+        return Err(anyhow!(
+            "{} elves but {} maximum lengths",
+            group.elves.len(),
+            max_lengths.len()
+        ));
+    }
+
+    //Flatten the covered sections, remembering which run each came from; a new range can't
+    //cross a gap or it would cover sections nobody covered before
+    let mut sections = vec![];
+    let mut run_of = vec![];
+    for (run, iv) in group.covered().iter().enumerate() {
+        for section in iv.start..=iv.end {
+            sections.push(section);
+            run_of.push(run);
+        }
+    }
+    let total = sections.len();
+
+    let mut order = (0..group.elves.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| (group.elves[i].start, group.elves[i].end));
+
+    //best[i][t]: most sections kept with the first i elves (in order) covering the first t
+    //sections; took[i][t]: how many sections elf i - 1 took to get there
+    let mut best: Vec<Vec<Option<u64>>> = vec![vec![None; total + 1]; order.len() + 1];
+    let mut took = vec![vec![0; total + 1]; order.len() + 1];
+    best[0][0] = Some(0);
+    for (i, &elf) in order.iter().enumerate() {
+        let old = group.elves[elf];
+        let max_len = max_lengths[elf] as usize;
+        for t in 0..=total {
+            let kept = match best[i][t] {
+                Some(kept) => kept,
+                None => continue,
+            };
+            if best[i + 1][t].is_none_or(|b| kept > b) {
+                best[i + 1][t] = Some(kept);
+                took[i + 1][t] = 0;
+            }
+            for len in 1..=max_len.min(total - t) {
+                if run_of[t + len - 1] != run_of[t] {
+                    break;
+                }
+                let piece = Interval::new(sections[t], sections[t + len - 1]);
+                let kept = kept + old.intersection(&piece).map_or(0, |both| both.size());
+                if best[i + 1][t + len].is_none_or(|b| kept > b) {
+                    best[i + 1][t + len] = Some(kept);
+                    took[i + 1][t + len] = len;
+                }
+            }
+        }
+    }
+
+    if best[order.len()][total].is_none() {
+        return Err(anyhow!(
+            "The maximum lengths are too small to cover all {} sections",
+            total
+        ));
+    }
+
+    let mut new = vec![None; group.elves.len()];
+    let mut t = total;
+    for i in (1..=order.len()).rev() {
+        let len = took[i][t];
+        if len > 0 {
+            new[order[i - 1]] = Some(Interval::new(sections[t - len], sections[t - 1]));
+            t -= len;
+        }
     }
+
+    let plan = Reassignment {
+        old: group.elves.clone(),
+        new,
+    };
+    plan.verify()?;
+    Ok(plan)
 }
 
 #[aoc_generator(day4)]
@@ -154,4 +423,102 @@ mod tests {
     fn example_part2() {
         assert_eq!(part2(&day4_parse(get_test_input())), 4);
     }
+
+    #[test]
+    fn example_groups_agree_with_pairs() {
+        //Every pair is a group of two, the matrix should tell the same story as part1 and part2
+        for (line, pair) in get_test_input().lines().zip(day4_parse(get_test_input())) {
+            let matrix = ElfGroup::new(line).unwrap().relation_matrix();
+            assert_eq!(matrix, ElfGroup::from(&pair).relation_matrix());
+            assert_eq!(matrix[0][0], Relation::Equal);
+            assert_eq!(
+                pair.one_in_pair_is_subset(),
+                matches!(
+                    matrix[0][1],
+                    Relation::Contains | Relation::ContainedBy | Relation::Equal
+                )
+            );
+            assert_eq!(pair.any_overlap(), matrix[0][1] != Relation::Disjoint);
+        }
+    }
+
+    #[test]
+    fn n_way_planning() {
+        let group = ElfGroup::new("2-4,3-9,8-12,10-11,20-25,20-22,23-25,1-1,11-11").unwrap();
+        let matrix = group.relation_matrix();
+        assert_eq!(matrix[0][1], Relation::Overlaps);
+        assert_eq!(matrix[2][3], Relation::Contains);
+        assert_eq!(matrix[3][2], Relation::ContainedBy);
+        assert_eq!(matrix[0][4], Relation::Disjoint);
+
+        let as_tuples =
+            |set: IntervalSet| set.iter().map(|iv| (iv.start, iv.end)).collect::<Vec<_>>();
+        assert_eq!(
+            as_tuples(group.uncovered(Interval::new(0, 30))),
+            vec![(0, 0), (13, 19), (26, 30)]
+        );
+        assert_eq!(
+            as_tuples(group.covered_by_more_than(1)),
+            vec![(3, 4), (8, 11), (20, 25)]
+        );
+        assert_eq!(as_tuples(group.covered_by_more_than(2)), vec![(11, 11)]);
+        assert!(group.covered_by_more_than(3).is_empty());
+
+        //1-1, 2-4, 3-9 and 8-12 chain together, and 20-25 alone does the job of 20-22 and 23-25
+        assert_eq!(group.minimal_cover(), vec![0, 1, 2, 4, 7]);
+        let cover = ElfGroup {
+            elves: group
+                .minimal_cover()
+                .iter()
+                .map(|&i| group.elves[i])
+                .collect(),
+        };
+        assert_eq!(cover.covered(), group.covered());
+
+        assert!(ElfGroup::new("2-4,6").is_err());
+        assert!(ElfGroup::new("5-4").is_err());
+    }
+
+    #[test]
+    fn example_reassignment() {
+        //Generous limits: overlapping pairs get split, contained elves go home, and nothing else
+        //changes
+        for pair in day4_parse(get_test_input()) {
+            let plan = reassign(&ElfGroup::from(&pair), &[100, 100]).unwrap();
+            let expected = if pair.any_overlap() { 1 } else { 0 };
+            assert_eq!(plan.changed(), expected);
+        }
+
+        let plan = reassign(&ElfGroup::new("2-8,3-7").unwrap(), &[100, 100]).unwrap();
+        assert_eq!(plan.new, vec![Some(Interval::new(2, 8)), None]);
+
+        //Capping the first elf at 3 means handing sections to the second, who would rather keep
+        //all of 3-7 and pick up 8 than give anything away
+        let plan = reassign(&ElfGroup::new("2-8,3-7").unwrap(), &[3, 100]).unwrap();
+        assert_eq!(plan.kept(), 6);
+        assert_eq!(plan.new[1], Some(Interval::new(3, 8)));
+        assert_eq!(
+            plan.to_string(),
+            "- elf 0: 2-8\n+ elf 0: 2-2\n- elf 1: 3-7\n+ elf 1: 3-8\n"
+        );
+
+        assert!(reassign(&ElfGroup::new("2-8,3-7").unwrap(), &[3, 3]).is_err());
+    }
+
+    #[test]
+    fn n_way_reassignment() {
+        let group = ElfGroup::new("1-5,4-9,8-12,20-25,22-30,24-26").unwrap();
+        let plan = reassign(&group, &[5, 5, 5, 6, 6, 3]).unwrap();
+        plan.verify().unwrap();
+        //4-9 is over its elf's cap of 5 and 20-30 needs two elves capped at 6, so the best is
+        //1-3, 4-7, 8-12 and then 20-24, 25-30 with 24-26 going home
+        assert_eq!(plan.kept(), 3 + 4 + 5 + 5 + 6);
+        assert_eq!(plan.new[2], Some(Interval::new(8, 12)));
+        assert_eq!(plan.new[5], None);
+        let kept_lengths = plan.new.iter().flatten().map(|iv| iv.size()).sum::<u64>();
+        assert_eq!(kept_lengths, group.covered().covered_len());
+        for (new, max) in plan.new.iter().zip([5, 5, 5, 6, 6, 3]) {
+            assert!(new.map_or(0, |iv| iv.size()) <= max);
+        }
+    }
 }
@@ -1,8 +1,13 @@
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::error::Error;
+use std::fmt;
 use std::usize;
 
-pub type Crate = char;
+// Usually one letter, but nothing stops a crate from being labelled [AB]
+pub type Crate = String;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Instruction {
     how_many: usize,
     from: usize,
@@ -10,7 +15,7 @@ pub struct Instruction {
 }
 
 impl Instruction {
-    fn new(line: &str) -> Self {
+    pub fn new(line: &str) -> Self {
         let mut out = line.split(" ");
         out.next().unwrap(); //move
 
@@ -26,97 +31,666 @@ impl Instruction {
     }
 }
 
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.how_many, self.from, self.to
+        )
+    }
+}
+
 pub type Stack = Vec<Crate>;
 type InputType = (Vec<Stack>, Vec<Instruction>);
 type OutputType = String;
 
+#[derive(Debug, PartialEq, Eq)]
+pub enum DrawingError {
+    // No numbered row at the bottom of the drawing
+    MissingLabels,
+    // Something in the label row that isn't a number
+    BadLabel { column: usize, text: String },
+    // Labels have to count up 1, 2, 3, ...
+    LabelsOutOfOrder { expected: usize, found: usize },
+    // Something in a crate row that isn't a [crate]
+    Malformed { row: usize, column: usize },
+    // A crate that doesn't sit above any label
+    NoStack { row: usize, column: usize },
+    // Two crates in the same row landed on the same stack
+    SharedStack { row: usize, stack: usize },
+    // A crate with nothing underneath it
+    FloatingCrate { row: usize, stack: usize },
+}
+
+impl fmt::Display for DrawingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DrawingError::MissingLabels => write!(f, "drawing has no label row"),
+            DrawingError::BadLabel { column, text } => {
+                write!(f, "label {:?} at column {} is not a number", text, column)
+            }
+            DrawingError::LabelsOutOfOrder { expected, found } => {
+                write!(f, "expected stack label {} but found {}", expected, found)
+            }
+            DrawingError::Malformed { row, column } => {
+                write!(f, "row {} column {} is not a crate", row, column)
+            }
+            DrawingError::NoStack { row, column } => write!(
+                f,
+                "crate on row {} column {} is not above any stack",
+                row, column
+            ),
+            DrawingError::SharedStack { row, stack } => {
+                write!(f, "row {} has two crates on stack {}", row, stack)
+            }
+            DrawingError::FloatingCrate { row, stack } => {
+                write!(f, "crate on row {} of stack {} is floating", row, stack)
+            }
+        }
+    }
+}
+
+impl Error for DrawingError {}
+
+// Runs of non-whitespace in a line, as (first column, last column, text)
+fn tokens(line: &str) -> Vec<(usize, usize, String)> {
+    let mut out = vec![];
+    let mut current: Option<(usize, String)> = None;
+    for (col, c) in line.chars().chain([' ']).enumerate() {
+        match (c.is_whitespace(), current.take()) {
+            (true, Some((start, text))) => out.push((start, col - 1, text)),
+            (true, None) => (),
+            (false, Some((start, mut text))) => {
+                text.push(c);
+                current = Some((start, text));
+            }
+            (false, None) => current = Some((col, c.to_string())),
+        }
+    }
+    out
+}
+
+// Reads the picture of the stacks, top row first and the numbered labels last. The labels decide
+// how many stacks there are and where they sit; each crate goes on the stack whose label is
+// closest to it, which copes with wide crates and rows that don't line up to the exact column.
+// Stacks come back bottom first, stacks[0] being the one labelled 1.
+pub fn parse_drawing(drawing: &str) -> Result<Vec<Stack>, DrawingError> {
+    let lines = drawing.lines().collect::<Vec<_>>();
+    let (label_line, crate_lines) = lines.split_last().ok_or(DrawingError::MissingLabels)?;
+
+    let labels = tokens(label_line);
+    if labels.is_empty() {
+        return Err(DrawingError::MissingLabels);
+    }
+    for (idx, (column, _, text)) in labels.iter().enumerate() {
+        let found = text.parse::<usize>().map_err(|_| DrawingError::BadLabel {
+            column: *column,
+            text: text.clone(),
+        })?;
+        if found != idx + 1 {
+            return Err(DrawingError::LabelsOutOfOrder {
+                expected: idx + 1,
+                found,
+            });
+        }
+    }
+
+    //rows[r][s] is whatever is on stack s in row r, counting rows from the top
+    let mut rows: Vec<Vec<Option<Crate>>> = vec![];
+    for (row, line) in crate_lines.iter().enumerate() {
+        let row = row + 1;
+        let mut slots = vec![None; labels.len()];
+        for (start, end, text) in tokens(line) {
+            if !(text.len() > 2 && text.starts_with('[') && text.ends_with(']')) {
+                return Err(DrawingError::Malformed { row, column: start });
+            }
+            //Compare doubled centres to stay in integers; only labels within a column of the
+            //crate count
+            let stack = labels
+                .iter()
+                .enumerate()
+                .filter(|(_, (l_start, l_end, _))| *l_start <= end + 1 && start <= *l_end + 1)
+                .min_by_key(|(_, (l_start, l_end, _))| {
+                    ((l_start + l_end) as i64 - (start + end) as i64).abs()
+                })
+                .map(|(idx, _)| idx)
+                .ok_or(DrawingError::NoStack { row, column: start })?;
+            if slots[stack].is_some() {
+                return Err(DrawingError::SharedStack {
+                    row,
+                    stack: stack + 1,
+                });
+            }
+            slots[stack] = Some(text[1..text.len() - 1].to_string());
+        }
+        rows.push(slots);
+    }
+
+    let mut stacks: Vec<Stack> = vec![vec![]; labels.len()];
+    for (row, slots) in rows.iter().enumerate().rev() {
+        for (stack, slot) in slots.iter().enumerate() {
+            if let Some(cr) = slot {
+                //Everything below has been pushed already, so the stack has to be exactly as
+                //tall as the number of rows underneath this one
+                if stacks[stack].len() != rows.len() - 1 - row {
+                    return Err(DrawingError::FloatingCrate {
+                        row: row + 1,
+                        stack: stack + 1,
+                    });
+                }
+                stacks[stack].push(cr.clone());
+            }
+        }
+    }
+    Ok(stacks)
+}
+
 #[aoc_generator(day5)]
 fn day5_parse(input: &str) -> InputType {
-    //Cheat here, instead of doing a prescan pass and determining how many stacks we have, assume
-    //we have no more than 19 (+1) for the intentionally left blank so I don't have to do index
-    //math, and be on your way; if I wanted this to be more resiliant, I would of course, do
-    //something fancier here (perhaps even just use a HashMap and map the values there)
-    let mut stacks: Vec<Stack> = Vec::new();
-    for _ in 0..20 {
-        stacks.push(vec![]);
-    }
-
-    let mut section_iter = input.split("\n\n");
-    let stacks_iter = section_iter.next().unwrap();
-    stacks_iter.split("\n").for_each(|line| {
-        line.chars()
-            .collect::<Vec<char>>()
-            .chunks(4)
-            .enumerate()
-            .for_each(|(stack_idx, qgram)| {
-                let qgram = qgram.iter().collect::<String>();
-                if qgram.contains("[") {
-                    let mut crate_chars = qgram.chars();
-                    crate_chars.next();
-                    stacks[stack_idx + 1 as usize].push(crate_chars.next().unwrap())
-                }
-            })
-    });
-    //Flip them over, could have used vecdeque but that doens't implement chunkable, these are tiny
-    //enough I don't care
-    let stacks = stacks
+    //The drawing ends at the first blank line, whitespace or not
+    let lines = input.lines().collect::<Vec<_>>();
+    let split = lines
         .iter()
-        .map(|stack| stack.into_iter().rev().map(|c| *c).collect::<Vec<_>>())
-        .collect::<Vec<_>>();
-    //println!("{:?}", stacks);
-
-    let instructions = section_iter
-        .next()
-        .unwrap()
-        .split("\n")
-        .map(|line| Instruction::new(line))
+        .position(|line| line.trim().is_empty())
+        .unwrap_or(lines.len());
+
+    let stacks = parse_drawing(&lines[..split].join("\n")).unwrap();
+
+    let instructions = lines[split..]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Instruction::new(line.trim()))
         .collect();
 
     (stacks, instructions)
 }
 
-#[aoc(day5, part1)]
-pub fn part1((stacks, instructions): &InputType) -> OutputType {
-    let mut stacks = stacks.clone();
-    for inst in instructions {
-        let Instruction { how_many, from, to } = inst;
-        for _ in 0..*how_many {
-            if let Some(val) = stacks[*from].pop() {
-                stacks[*to].push(val);
-                // println!("Moving from {} to {} = {}",from,to,val);
-                // println!("{:?}",stacks);
-            }
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    // The instruction names a stack that isn't in the drawing
+    NoSuchStack(usize),
+    // A strict crane was asked to lift more than the stack holds
+    NotEnoughCrates {
+        stack: usize,
+        wanted: usize,
+        available: usize,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MoveError::NoSuchStack(stack) => write!(f, "there is no stack {}", stack),
+            MoveError::NotEnoughCrates {
+                stack,
+                wanted,
+                available,
+            } => write!(
+                f,
+                "can't move {} crates from stack {}, it only has {}",
+                wanted, stack, available
+            ),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+// How a crane carries out one instruction. Stacks are bottom first and stacks[0] is stack 1,
+// the same as parse_drawing hands them out
+pub trait CrateMover {
+    fn apply(&self, stacks: &mut [Stack], inst: &Instruction) -> Result<(), MoveError>;
+}
+
+fn check_stacks(stacks: &[Stack], inst: &Instruction) -> Result<(), MoveError> {
+    for stack in [inst.from, inst.to] {
+        if stack == 0 || stack > stacks.len() {
+            return Err(MoveError::NoSuchStack(stack));
+        }
+    }
+    Ok(())
+}
+
+// Takes up to how_many crates off the top of a stack, still in bottom first order. Running out
+// of crates part way isn't an error, the puzzle's cranes just move what's there
+fn lift(stacks: &mut [Stack], from: usize, how_many: usize) -> Vec<Crate> {
+    let stack = &mut stacks[from - 1];
+    let keep = stack.len().saturating_sub(how_many);
+    stack.split_off(keep)
+}
+
+// One crate at a time, so a batch lands upside down
+pub struct CrateMover9000;
+
+impl CrateMover for CrateMover9000 {
+    fn apply(&self, stacks: &mut [Stack], inst: &Instruction) -> Result<(), MoveError> {
+        check_stacks(stacks, inst)?;
+        let lifted = lift(stacks, inst.from, inst.how_many);
+        stacks[inst.to - 1].extend(lifted.into_iter().rev());
+        Ok(())
+    }
+}
+
+// The whole batch at once, order kept
+pub struct CrateMover9001;
+
+impl CrateMover for CrateMover9001 {
+    fn apply(&self, stacks: &mut [Stack], inst: &Instruction) -> Result<(), MoveError> {
+        check_stacks(stacks, inst)?;
+        let lifted = lift(stacks, inst.from, inst.how_many);
+        stacks[inst.to - 1].extend(lifted);
+        Ok(())
+    }
+}
+
+// Moves batches like a 9001 but can only hold max_lift crates, so bigger moves turn into several
+// trips. A max_lift of 1 is a 9000
+pub struct LimitedLift {
+    pub max_lift: usize,
+}
+
+impl CrateMover for LimitedLift {
+    fn apply(&self, stacks: &mut [Stack], inst: &Instruction) -> Result<(), MoveError> {
+        check_stacks(stacks, inst)?;
+        let mut remaining = inst.how_many;
+        while remaining > 0 && !stacks[inst.from - 1].is_empty() {
+            let trip = remaining.min(self.max_lift.max(1));
+            let lifted = lift(stacks, inst.from, trip);
+            stacks[inst.to - 1].extend(lifted);
+            remaining -= trip;
         }
+        Ok(())
     }
-    //println!("{:?}",stacks);
+}
+
+// Wraps another crane, refusing any instruction that asks for more crates than there are instead
+// of quietly moving fewer
+pub struct Strict<M: CrateMover>(pub M);
+
+impl<M: CrateMover> CrateMover for Strict<M> {
+    fn apply(&self, stacks: &mut [Stack], inst: &Instruction) -> Result<(), MoveError> {
+        check_stacks(stacks, inst)?;
+        let available = stacks[inst.from - 1].len();
+        if available < inst.how_many {
+            return Err(MoveError::NotEnoughCrates {
+                stack: inst.from,
+                wanted: inst.how_many,
+                available,
+            });
+        }
+        self.0.apply(stacks, inst)
+    }
+}
+
+pub fn tops(stacks: &[Stack]) -> String {
     stacks
         .iter()
-        .filter_map(|stack| stack.last())
+        .filter_map(|stack| stack.last().map(|cr| cr.as_str()))
         .collect::<String>()
 }
 
-#[aoc(day5, part2)]
-pub fn part2((stacks, instructions): &InputType) -> OutputType {
-    let mut stacks = stacks.clone();
+pub fn run<M: CrateMover>(
+    stacks: &[Stack],
+    instructions: &[Instruction],
+    mover: &M,
+) -> Result<Vec<Stack>, MoveError> {
+    let mut stacks = stacks.to_vec();
     for inst in instructions {
-        let Instruction { how_many, from, to } = inst;
-        let mut handle = VecDeque::new();
-        for _ in 0..*how_many {
-            if let Some(val) = stacks[*from].pop() {
-                handle.push_front(val);
-                // println!("Moving from {} to {} = {}",from,to,val);
-                // println!("{:?}",stacks);
+        mover.apply(&mut stacks, inst)?;
+    }
+    Ok(stacks)
+}
+
+// Draws the stacks the way the puzzle does, and the way parse_drawing reads them back. Every
+// column is as wide as the widest crate, so [A] and [BCD] still line up under their labels.
+// highlight(stack, level) crates get <X> instead of [X]
+fn draw(stacks: &[Stack], highlight: &dyn Fn(usize, usize) -> bool) -> String {
+    let width = stacks
+        .iter()
+        .flatten()
+        .map(|cr| cr.chars().count() + 2)
+        .max()
+        .unwrap_or(3)
+        .max(3);
+    let height = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+
+    let mut lines = vec![];
+    for level in (0..height).rev() {
+        let line = stacks
+            .iter()
+            .enumerate()
+            .map(|(idx, stack)| match stack.get(level) {
+                Some(cr) if highlight(idx, level) => format!("{:<width$}", format!("<{}>", cr)),
+                Some(cr) => format!("{:<width$}", format!("[{}]", cr)),
+                None => " ".repeat(width),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(line.trim_end().to_string());
+    }
+    let labels = (1..=stacks.len())
+        .map(|label| format!("{:^width$}", label))
+        .collect::<Vec<_>>()
+        .join(" ");
+    lines.push(labels.trim_end().to_string());
+    lines.join("\n")
+}
+
+pub fn render(stacks: &[Stack]) -> String {
+    draw(stacks, &|_, _| false)
+}
+
+// What a single step did: which crates moved, listed as they now sit on the destination (bottom
+// first)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Moved {
+    pub step: usize,
+    pub instruction: Instruction,
+    pub crates: Vec<Crate>,
+}
+
+impl fmt::Display for Moved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "step {}: {} ({})",
+            self.step,
+            self.instruction,
+            self.crates.join(" ")
+        )
+    }
+}
+
+// Enough to put things back: an instruction only ever touches its from and to stacks
+struct Undo {
+    from_before: Stack,
+    to_before: Stack,
+    moved: usize,
+}
+
+// Steps through a plan one instruction at a time, forwards or backwards
+pub struct Simulation<M: CrateMover> {
+    mover: M,
+    instructions: Vec<Instruction>,
+    stacks: Vec<Stack>,
+    history: Vec<Undo>,
+}
+
+impl<M: CrateMover> Simulation<M> {
+    pub fn new(stacks: Vec<Stack>, instructions: Vec<Instruction>, mover: M) -> Self {
+        Simulation {
+            mover,
+            instructions,
+            stacks,
+            history: vec![],
+        }
+    }
+
+    // Number of instructions applied so far
+    pub fn position(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn steps(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    fn moved(&self, step: usize, moved: usize) -> Moved {
+        let instruction = self.instructions[step - 1];
+        let to = &self.stacks[instruction.to - 1];
+        Moved {
+            step,
+            instruction,
+            crates: to[to.len() - moved..].to_vec(),
+        }
+    }
+
+    // Applies the next instruction, None once there are none left. A failed step leaves the
+    // stacks as they were
+    pub fn step(&mut self) -> Result<Option<Moved>, MoveError> {
+        let inst = match self.instructions.get(self.position()) {
+            Some(inst) => *inst,
+            None => return Ok(None),
+        };
+        check_stacks(&self.stacks, &inst)?;
+        let from_before = self.stacks[inst.from - 1].clone();
+        let to_before = self.stacks[inst.to - 1].clone();
+        if let Err(e) = self.mover.apply(&mut self.stacks, &inst) {
+            self.stacks[inst.from - 1] = from_before;
+            self.stacks[inst.to - 1] = to_before;
+            return Err(e);
+        }
+        let moved = inst.how_many.min(from_before.len());
+        self.history.push(Undo {
+            from_before,
+            to_before,
+            moved,
+        });
+        Ok(Some(self.moved(self.position(), moved)))
+    }
+
+    // Undoes the last instruction, handing back what it had moved
+    pub fn back(&mut self) -> Option<Moved> {
+        let step = self.position();
+        let moved = self.history.last()?.moved;
+        let undone = self.moved(step, moved);
+        let undo = self.history.pop()?;
+        let inst = self.instructions[step - 1];
+        self.stacks[inst.to - 1] = undo.to_before;
+        self.stacks[inst.from - 1] = undo.from_before;
+        Some(undone)
+    }
+
+    // Goes to the state after the first `step` instructions, either way
+    pub fn seek(&mut self, step: usize) -> Result<(), MoveError> {
+        let step = step.min(self.steps());
+        while self.position() > step {
+            self.back();
+        }
+        while self.position() < step {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    pub fn render(&self) -> String {
+        render(&self.stacks)
+    }
+
+    // The drawing with whatever the last step moved shown as <X>
+    pub fn render_diff(&self) -> String {
+        let (stack, moved) = match self.history.last() {
+            Some(undo) => (self.instructions[self.position() - 1].to - 1, undo.moved),
+            None => return self.render(),
+        };
+        let height = self.stacks[stack].len();
+        draw(&self.stacks, &|idx, level| {
+            idx == stack && level + moved >= height
+        })
+    }
+}
+
+// What a plan has to end with: either exactly this layout, or just these crates on top (None
+// being "don't care")
+pub enum Target {
+    Layout(Vec<Stack>),
+    Tops(Vec<Option<Crate>>),
+}
+
+impl Target {
+    fn reached(&self, stacks: &[Stack]) -> bool {
+        match self {
+            Target::Layout(layout) => stacks == &layout[..],
+            Target::Tops(wanted) => wanted
+                .iter()
+                .zip(stacks.iter())
+                .all(|(want, stack)| want.is_none() || want.as_ref() == stack.last()),
+        }
+    }
+
+    // A lower bound on the moves still needed, so the search below finds a shortest plan.
+    // Every move has one source and one destination: a stack holding something it shouldn't
+    // needs at least one move out of it, a stack missing something needs at least one move into
+    // it, and a wrong top needs the stack on one end or the other of some move
+    fn estimate(&self, stacks: &[Stack]) -> usize {
+        match self {
+            Target::Layout(layout) => {
+                let mut sources = 0;
+                let mut destinations = 0;
+                for (stack, want) in stacks.iter().zip(layout.iter()) {
+                    if !want.starts_with(stack) {
+                        sources += 1;
+                    } else if stack.len() < want.len() {
+                        destinations += 1;
+                    }
+                }
+                sources.max(destinations)
+            }
+            Target::Tops(wanted) => {
+                let wrong = wanted
+                    .iter()
+                    .zip(stacks.iter())
+                    .filter(|(want, stack)| want.is_some() && want.as_ref() != stack.last())
+                    .count();
+                wrong.div_ceil(2)
             }
         }
-        for cr in handle.into_iter() {
-            stacks[*to].push(cr);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlanError {
+    // The target doesn't have the same number of stacks
+    StackCount { have: usize, want: usize },
+    // The target layout isn't made of the same crates
+    DifferentCrates,
+    // Searched everything reachable without getting there
+    Unreachable,
+    // Looked at max_states layouts and gave up
+    GaveUp { explored: usize },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanError::StackCount { have, want } => {
+                write!(f, "have {} stacks but the target has {}", have, want)
+            }
+            PlanError::DifferentCrates => write!(f, "target layout has different crates"),
+            PlanError::Unreachable => write!(f, "target can't be reached with this crane"),
+            PlanError::GaveUp { explored } => {
+                write!(f, "no plan found after looking at {} layouts", explored)
+            }
         }
     }
+}
 
-    stacks
+impl Error for PlanError {}
+
+// The other way round from part1/part2: find the fewest instructions that get a crane from
+// `stacks` to `target`. A* over whole layouts with Target::estimate as the heuristic; every move
+// of every possible size is tried, so this is for drawings the size of the example, not the
+// real input. max_states bounds how many layouts get expanded.
+pub fn plan<M: CrateMover>(
+    stacks: &[Stack],
+    target: &Target,
+    mover: &M,
+    max_states: usize,
+) -> Result<Vec<Instruction>, PlanError> {
+    let want = match target {
+        Target::Layout(layout) => layout.len(),
+        Target::Tops(tops) => tops.len(),
+    };
+    if want != stacks.len() {
+        return Err(PlanError::StackCount {
+            have: stacks.len(),
+            want,
+        });
+    }
+    if let Target::Layout(layout) = target {
+        let mut have = stacks.iter().flatten().collect::<Vec<_>>();
+        let mut need = layout.iter().flatten().collect::<Vec<_>>();
+        have.sort();
+        need.sort();
+        if have != need {
+            return Err(PlanError::DifferentCrates);
+        }
+    }
+
+    //Every layout seen, with how it was reached (parent index and the move from there)
+    type Node = (Vec<Stack>, Option<(usize, Instruction)>);
+    let mut nodes: Vec<Node> = vec![(stacks.to_vec(), None)];
+    let mut best: HashMap<Vec<Stack>, usize> = HashMap::new();
+    best.insert(stacks.to_vec(), 0);
+    let mut queue = BinaryHeap::new();
+    queue.push(Reverse((target.estimate(stacks), 0, 0)));
+
+    let mut explored = 0;
+    while let Some(Reverse((_, moves, idx))) = queue.pop() {
+        if best.get(&nodes[idx].0) != Some(&moves) {
+            continue;
+        }
+        if target.reached(&nodes[idx].0) {
+            let mut out = vec![];
+            let mut at = idx;
+            while let Some((parent, inst)) = nodes[at].1 {
+                out.push(inst);
+                at = parent;
+            }
+            out.reverse();
+            return Ok(out);
+        }
+        explored += 1;
+        if explored > max_states {
+            return Err(PlanError::GaveUp {
+                explored: max_states,
+            });
+        }
+
+        for from in 1..=stacks.len() {
+            for to in (1..=stacks.len()).filter(|&to| to != from) {
+                for how_many in 1..=nodes[idx].0[from - 1].len() {
+                    let inst = Instruction { how_many, from, to };
+                    let mut next = nodes[idx].0.clone();
+                    if mover.apply(&mut next, &inst).is_err() {
+                        continue;
+                    }
+                    if best.get(&next).is_some_and(|&seen| seen <= moves + 1) {
+                        continue;
+                    }
+                    best.insert(next.clone(), moves + 1);
+                    let guess = moves + 1 + target.estimate(&next);
+                    nodes.push((next, Some((idx, inst))));
+                    queue.push(Reverse((guess, moves + 1, nodes.len() - 1)));
+                }
+            }
+        }
+    }
+    Err(PlanError::Unreachable)
+}
+
+// One instruction per line, the same text Instruction::new reads
+pub fn plan_text(instructions: &[Instruction]) -> String {
+    instructions
         .iter()
-        .filter_map(|stack| stack.last())
-        .collect::<String>()
+        .map(|inst| inst.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[aoc(day5, part1)]
+pub fn part1((stacks, instructions): &InputType) -> OutputType {
+    tops(&run(stacks, instructions, &CrateMover9000).unwrap())
+}
+
+#[aoc(day5, part2)]
+pub fn part2((stacks, instructions): &InputType) -> OutputType {
+    tops(&run(stacks, instructions, &CrateMover9001).unwrap())
 }
 
 #[cfg(test)]
@@ -145,4 +719,241 @@ move 1 from 1 to 2"
     fn day5_part2() {
         assert_eq!(part2(&day5_parse(get_test_input())), "MCD");
     }
+
+    #[test]
+    fn day5_wide_drawing() {
+        //35 stacks of two letter crates, stack n being n % 7 high, with the odd trailing space
+        //and the label row indented differently to the crates
+        let heights = (1..=35).map(|n| n % 7).collect::<Vec<_>>();
+        let name = |stack: usize, level: usize| {
+            format!(
+                "{}{}",
+                (b'A' + (stack % 26) as u8) as char,
+                (b'a' + level as u8) as char
+            )
+        };
+        let mut drawing = vec![];
+        for level in (0..6).rev() {
+            let row = heights
+                .iter()
+                .enumerate()
+                .map(|(stack, &h)| {
+                    if level < h {
+                        format!("[{}]", name(stack, level))
+                    } else {
+                        "    ".to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            drawing.push(format!("{}{}", row.trim_end(), " ".repeat(level % 3)));
+        }
+        drawing.push(
+            (1..=35)
+                .map(|n| format!("{:^4}", n))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+
+        let stacks = parse_drawing(&drawing.join("\n")).unwrap();
+        assert_eq!(stacks.len(), 35);
+        for (stack, &h) in heights.iter().enumerate() {
+            let expected = (0..h).map(|level| name(stack, level)).collect::<Vec<_>>();
+            assert_eq!(stacks[stack], expected);
+        }
+
+        let input = format!(
+            "{}\n\nmove 2 from 34 to 33\nmove 1 from 33 to 1",
+            drawing.join("\n")
+        );
+        assert_eq!(part1(&day5_parse(&input)).len(), 30 * 2);
+    }
+
+    #[test]
+    fn day5_bad_drawings() {
+        assert_eq!(
+            parse_drawing("[A]\n    [B]\n 1   2"),
+            Err(DrawingError::FloatingCrate { row: 1, stack: 1 })
+        );
+        assert_eq!(
+            parse_drawing("[A] [B]\n 1   3"),
+            Err(DrawingError::LabelsOutOfOrder {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            parse_drawing("[A] [B] [C]\n 1   2"),
+            Err(DrawingError::NoStack { row: 1, column: 8 })
+        );
+        assert_eq!(
+            parse_drawing("[A] B\n 1   2"),
+            Err(DrawingError::Malformed { row: 1, column: 4 })
+        );
+        assert_eq!(
+            parse_drawing("[A] [B]\n 1   x"),
+            Err(DrawingError::BadLabel {
+                column: 5,
+                text: "x".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn day5_crane_models() {
+        let (stacks, instructions) = day5_parse(get_test_input());
+        let limited =
+            |max_lift| tops(&run(&stacks, &instructions, &LimitedLift { max_lift }).unwrap());
+        assert_eq!(limited(1), part1(&(stacks.clone(), instructions.clone())));
+        assert_eq!(limited(3), part2(&(stacks.clone(), instructions.clone())));
+        //The 3 crate move goes as N D then Z, and the later 2 crate move goes in one trip
+        assert_eq!(limited(2), "MCZ");
+        let after = run(&stacks, &instructions, &LimitedLift { max_lift: 2 }).unwrap();
+        assert_eq!(after[2], vec!["P", "N", "D", "Z"]);
+
+        //The example never over-asks, so strict cranes agree with the lenient ones
+        assert_eq!(
+            run(&stacks, &instructions, &Strict(CrateMover9001)).unwrap(),
+            run(&stacks, &instructions, &CrateMover9001).unwrap()
+        );
+
+        let empty_move = [Instruction::new("move 4 from 3 to 1")];
+        let lenient = run(&stacks, &empty_move, &CrateMover9000).unwrap();
+        assert_eq!(lenient[0], vec!["Z", "N", "P"]);
+        assert_eq!(
+            run(&stacks, &empty_move, &Strict(CrateMover9000)),
+            Err(MoveError::NotEnoughCrates {
+                stack: 3,
+                wanted: 4,
+                available: 1
+            })
+        );
+        //Asking for nothing from an empty stack isn't asking for too much
+        let nothing_from_empty = [
+            Instruction::new("move 1 from 3 to 1"),
+            Instruction::new("move 0 from 3 to 2"),
+        ];
+        assert_eq!(
+            run(&stacks, &nothing_from_empty, &Strict(CrateMover9000)),
+            run(&stacks, &nothing_from_empty, &CrateMover9000)
+        );
+        assert!(run(&stacks, &nothing_from_empty, &Strict(CrateMover9000)).is_ok());
+        assert_eq!(
+            run(
+                &stacks,
+                &[Instruction::new("move 1 from 4 to 1")],
+                &CrateMover9001
+            ),
+            Err(MoveError::NoSuchStack(4))
+        );
+    }
+
+    #[test]
+    fn day5_simulation() {
+        let (stacks, instructions) = day5_parse(get_test_input());
+        let mut sim = Simulation::new(stacks.clone(), instructions.clone(), CrateMover9000);
+
+        //The example's drawing, tidied up to line up with its labels
+        assert_eq!(sim.render(), "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3");
+
+        let moved = sim.step().unwrap().unwrap();
+        assert_eq!(moved.to_string(), "step 1: move 1 from 2 to 1 (D)");
+        let moved = sim.step().unwrap().unwrap();
+        assert_eq!(moved.crates, vec!["D", "N", "Z"]);
+        assert_eq!(
+            sim.render_diff(),
+            "        <Z>\n        <N>\n    [C] <D>\n    [M] [P]\n 1   2   3"
+        );
+
+        //Every state parses back to itself, and stepping back retraces the same states
+        let mut seen = vec![sim.stacks().to_vec()];
+        sim.seek(0).unwrap();
+        for _ in 0..sim.steps() {
+            assert_eq!(parse_drawing(&sim.render()).unwrap(), sim.stacks());
+            sim.step().unwrap();
+            seen.push(sim.stacks().to_vec());
+        }
+        assert_eq!(sim.step(), Ok(None));
+        assert_eq!(tops(sim.stacks()), "CMZ");
+        sim.seek(2).unwrap();
+        assert_eq!(sim.stacks(), &seen[0][..]);
+        while sim.back().is_some() {}
+        assert_eq!(sim.stacks(), &stacks[..]);
+
+        //Wide crates still round trip
+        let wide = vec![
+            vec!["A".to_string(), "BCD".to_string()],
+            vec![],
+            vec!["EF".to_string()],
+        ];
+        assert_eq!(parse_drawing(&render(&wide)).unwrap(), wide);
+
+        //A strict crane refusing a step leaves everything untouched
+        let mut sim = Simulation::new(
+            stacks.clone(),
+            vec![Instruction::new("move 3 from 3 to 1")],
+            Strict(CrateMover9001),
+        );
+        assert!(sim.step().is_err());
+        assert_eq!(sim.position(), 0);
+        assert_eq!(sim.stacks(), &stacks[..]);
+    }
+
+    #[test]
+    fn day5_planner() {
+        let (stacks, instructions) = day5_parse(get_test_input());
+
+        //The example takes 4 moves to get to its part1 layout, the planner should find no longer
+        //a way, and its text should drive part1 to the same answer
+        let goal = run(&stacks, &instructions, &CrateMover9000).unwrap();
+        let found = plan(
+            &stacks,
+            &Target::Layout(goal.clone()),
+            &CrateMover9000,
+            10000,
+        )
+        .unwrap();
+        assert!(found.len() <= 4);
+        let replay = plan_text(&found)
+            .lines()
+            .map(Instruction::new)
+            .collect::<Vec<_>>();
+        assert_eq!(replay, found);
+        assert_eq!(run(&stacks, &replay, &CrateMover9000).unwrap(), goal);
+        assert_eq!(
+            part1(&(stacks.clone(), replay)),
+            part1(&(stacks.clone(), instructions.clone()))
+        );
+
+        //N on top of stack 3 while D stays put on stack 2 is a single move
+        let tops = Target::Tops(vec![None, Some("D".to_string()), Some("N".to_string())]);
+        let found = plan(&stacks, &tops, &CrateMover9001, 10000).unwrap();
+        assert_eq!(plan_text(&found), "move 1 from 1 to 3");
+
+        //Z on top of stack 3: a 9000 flips Z N over in one go, a 9001 keeps their order so N has
+        //to be put somewhere else first
+        let tops = Target::Tops(vec![None, None, Some("Z".to_string())]);
+        let found = plan(&stacks, &tops, &CrateMover9000, 10000).unwrap();
+        assert_eq!(plan_text(&found), "move 2 from 1 to 3");
+        let found = plan(&stacks, &tops, &CrateMover9001, 10000).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(
+            run(&stacks, &found, &CrateMover9001).unwrap()[2].last(),
+            Some(&"Z".to_string())
+        );
+
+        assert_eq!(
+            plan(
+                &stacks,
+                &Target::Layout(vec![vec![]; 3]),
+                &CrateMover9000,
+                10
+            ),
+            Err(PlanError::DifferentCrates)
+        );
+        assert_eq!(
+            plan(&stacks, &Target::Tops(vec![None]), &CrateMover9000, 10),
+            Err(PlanError::StackCount { have: 3, want: 1 })
+        );
+    }
 }
@@ -1,8 +1,12 @@
-use std::cell::RefCell;
-use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::RangeBounds;
+use std::path::Path;
 
-type InputType = Rc<RefCell<Dir>>;
+type InputType = FileSystem;
 type OutputType = usize;
 
 //NOTES:
@@ -10,28 +14,52 @@ type OutputType = usize;
 // painful (though, I was able to just up and write the solutions really easily). I think there are
 // two ways of making this not horrible: Change my data structure, somehow, or figure out a better
 // way of accessing so I don't have to do all the Rc -> RefCell unmapping
-// I could possibly just keep a map of full paths and just stick the dirs in there without having
-// to deal with all the refcell crap,
 //
-// TODO: Implement this without direct refs and just keep a master HashMap/BtreeMap of full paths,
-// then I have to parse strings to find paths, but that's not a huge deal, should prevent me from
-// having to have all this refcell nonsense.
+// Went with changing the data structure: everything lives in one Vec and refers to everything
+// else by index, so there's nothing to borrow. Each directory keeps a name -> index map of what's
+// in it and each node knows its parent, which is all `cd` needs.
+
+pub type NodeId = usize;
+
+// The root is always the first node
+pub const ROOT: NodeId = 0;
 
 #[derive(Debug)]
 pub struct Dir {
     name: String,
-    full_path: String,
-    dirs: Vec<Rc<RefCell<Dir>>>,
-    files: Vec<File>,
+    children: BTreeMap<String, NodeId>,
+}
+
+#[derive(Debug)]
+pub struct File {
+    name: String,
+    size: usize,
+}
+
+#[derive(Debug)]
+pub enum Entry {
+    Dir(Dir),
+    File(File),
+}
+
+#[derive(Debug)]
+pub struct Node {
+    parent: Option<NodeId>,
+    entry: Entry,
+    // Everything underneath, kept up to date as files come and go
+    size: usize,
+}
+
+#[derive(Debug)]
+pub struct FileSystem {
+    nodes: Vec<Node>,
 }
 
 impl Dir {
-    fn new(path: &str, name: &str) -> Self {
+    fn new(name: &str) -> Self {
         Dir {
             name: name.to_string(),
-            full_path: path.to_string(),
-            dirs: vec![],
-            files: vec![],
+            children: BTreeMap::new(),
         }
     }
 }
@@ -45,158 +73,849 @@ impl File {
     }
 }
 
-#[derive(Debug)]
-pub struct File {
-    #[allow(dead_code)]
-    name: String,
-    size: usize,
+impl Default for FileSystem {
+    fn default() -> Self {
+        FileSystem::new()
+    }
 }
 
-fn get_subdir_if_exists(dir: &Rc<RefCell<Dir>>, dir_name: &str) -> Option<Rc<RefCell<Dir>>> {
-    let next_dir_ref = dir.as_ref().clone().borrow();
-    next_dir_ref
-        .dirs
-        .iter()
-        .cloned()
-        .find(|d| d.as_ref().borrow().name.to_owned() == dir_name)
+impl FileSystem {
+    pub fn new() -> Self {
+        FileSystem {
+            nodes: vec![Node {
+                parent: None,
+                entry: Entry::Dir(Dir::new("/")),
+                size: 0,
+            }],
+        }
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn name(&self, id: NodeId) -> &str {
+        match &self.nodes[id].entry {
+            Entry::Dir(dir) => &dir.name,
+            Entry::File(file) => &file.name,
+        }
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.nodes[id].parent
+    }
+
+    pub fn is_dir(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id].entry, Entry::Dir(_))
+    }
+
+    // Total size of everything under a directory, or a file's own size
+    pub fn size(&self, id: NodeId) -> usize {
+        self.nodes[id].size
+    }
+
+    pub fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].entry {
+            Entry::Dir(dir) => dir.children.get(name).copied(),
+            Entry::File(_) => None,
+        }
+    }
+
+    // Everything directly inside a directory, in name order
+    pub fn children(&self, dir: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[dir].entry {
+            Entry::Dir(dir) => Some(dir.children.values().copied()),
+            Entry::File(_) => None,
+        };
+        children.into_iter().flatten()
+    }
+
+    fn add(&mut self, parent: NodeId, entry: Entry) -> NodeId {
+        let id = self.nodes.len();
+        let (name, size) = match &entry {
+            Entry::Dir(dir) => (dir.name.clone(), 0),
+            Entry::File(file) => (file.name.clone(), file.size),
+        };
+        self.nodes.push(Node {
+            parent: Some(parent),
+            entry,
+            size: 0,
+        });
+        match &mut self.nodes[parent].entry {
+            Entry::Dir(dir) => {
+                dir.children.insert(name, id);
+            }
+            Entry::File(_) => panic!("Can't put anything inside a file"),
+        }
+        self.grow(id, size as isize);
+        id
+    }
+
+    // Adjusts the cached size of a node and everything above it
+    fn grow(&mut self, id: NodeId, by: isize) {
+        let mut at = Some(id);
+        while let Some(id) = at {
+            self.nodes[id].size = (self.nodes[id].size as isize + by) as usize;
+            at = self.nodes[id].parent;
+        }
+    }
+
+    // The directory called `name` in `parent`, made if it isn't there yet
+    pub fn mkdir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        match self.child(parent, name) {
+            Some(id) => id,
+            None => self.add(parent, Entry::Dir(Dir::new(name))),
+        }
+    }
+
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> NodeId {
+        match self.child(parent, name) {
+            Some(id) => id,
+            None => self.add(parent, Entry::File(File::new(name, size))),
+        }
+    }
+
+    // Detaches a file or a whole directory from its parent. The nodes stay in the arena but
+    // nothing can reach them any more
+    pub fn remove(&mut self, id: NodeId) {
+        let parent = match self.parent(id) {
+            Some(parent) => parent,
+            None => panic!("Can't remove the root"),
+        };
+        let name = self.name(id).to_string();
+        if let Entry::Dir(dir) = &mut self.nodes[parent].entry {
+            dir.children.remove(&name);
+        }
+        let size = self.size(id);
+        self.grow(parent, -(size as isize));
+        self.nodes[id].parent = None;
+    }
+
+    pub fn resize(&mut self, file: NodeId, size: usize) {
+        if let Entry::File(f) = &mut self.nodes[file].entry {
+            let by = size as isize - f.size as isize;
+            f.size = size;
+            self.grow(file, by);
+        }
+    }
+
+    // Is `id` the same as or somewhere under `dir`
+    pub fn is_within(&self, id: NodeId, dir: NodeId) -> bool {
+        let mut at = Some(id);
+        while let Some(node) = at {
+            if node == dir {
+                return true;
+            }
+            at = self.parent(node);
+        }
+        false
+    }
+
+    // Follows a path from `from`: "/a/b" starts at the root, "a/b" from `from`, ".." goes up
+    // (staying put at the root) and "." or empty segments do nothing
+    pub fn resolve(&self, from: NodeId, path: &str) -> Option<NodeId> {
+        let mut at = if path.starts_with('/') { ROOT } else { from };
+        for segment in path.split('/') {
+            at = match segment {
+                "" | "." => at,
+                ".." => self.parent(at).unwrap_or(ROOT),
+                name => self.child(at, name)?,
+            };
+        }
+        Some(at)
+    }
+
+    // "/a/b" style, the root being just "/"
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut at = id;
+        while let Some(parent) = self.parent(at) {
+            names.push(self.name(at));
+            at = parent;
+        }
+        names.reverse();
+        format!("/{}", names.join("/"))
+    }
+
+    // Everything under `from` (itself included) with how deep it is, parents before children
+    // and each directory's contents in name order
+    pub fn walk(&self, from: NodeId) -> Vec<(NodeId, usize)> {
+        let mut out = vec![];
+        let mut to_visit = vec![(from, 0)];
+        while let Some((id, depth)) = to_visit.pop() {
+            out.push((id, depth));
+            let mut children = self.children(id).collect::<Vec<_>>();
+            children.reverse();
+            to_visit.extend(children.into_iter().map(|child| (child, depth + 1)));
+        }
+        out
+    }
+
+    // Every directory, parents before children
+    pub fn dirs(&self) -> Vec<NodeId> {
+        self.walk(ROOT)
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|&id| self.is_dir(id))
+            .collect()
+    }
+}
+
+// Shell style * and ? wildcards, matched against a whole name
+fn glob(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            glob(&pattern[1..], name) || (!name.is_empty() && glob(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => glob(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => glob(&pattern[1..], &name[1..]),
+        _ => false,
+    }
 }
 
-fn calculate_naive_dir_size(
-    dir: &Rc<RefCell<Dir>>,
-    all_dirs: &mut BTreeMap<String, usize>,
-) -> usize {
-    let mut size = 0;
-    let current_dir_name = dir.as_ref().borrow().name.clone();
-    let cur_path = dir.as_ref().borrow().full_path.clone();
-    //println!("Looking at {}",current_dir_name);
-    for file in dir.as_ref().clone().borrow().files.iter() {
-        size += file.size;
+// Ways of looking around the tree once it's built
+impl FileSystem {
+    // The puzzle's own drawing of the tree, with directory sizes filled in
+    pub fn tree(&self, from: NodeId) -> String {
+        self.walk(from)
+            .into_iter()
+            .map(|(id, depth)| {
+                let what = match &self.nodes[id].entry {
+                    Entry::Dir(dir) => format!("{} (dir, size={})", dir.name, self.size(id)),
+                    Entry::File(file) => format!("{} (file, size={})", file.name, file.size),
+                };
+                format!("{}- {}", "  ".repeat(depth), what)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
     }
-    for dir in dir.as_ref().borrow().dirs.iter() {
-        size += calculate_naive_dir_size(&dir, all_dirs);
+
+    // Like du -d max_depth: directories no more than max_depth below the root with their total
+    // sizes, each listed after everything inside it
+    pub fn du(&self, max_depth: usize) -> Vec<(String, usize)> {
+        let mut out = self
+            .walk(ROOT)
+            .into_iter()
+            .filter(|&(id, depth)| self.is_dir(id) && depth <= max_depth)
+            .map(|(id, _)| (self.path(id), self.size(id)))
+            .collect::<Vec<_>>();
+        //Siblings in name order, but anything inside a directory comes before it
+        out.sort_by(|(a, _), (b, _)| {
+            let a = a.trim_end_matches('/').split('/').collect::<Vec<_>>();
+            let b = b.trim_end_matches('/').split('/').collect::<Vec<_>>();
+            match a.iter().zip(b.iter()).find(|(x, y)| x != y) {
+                Some((x, y)) => x.cmp(y),
+                None => b.len().cmp(&a.len()),
+            }
+        });
+        out
+    }
+
+    // Files and directories whose name matches `pattern` and whose size is in `sizes`; "*"
+    // and .. match anything
+    pub fn find<R: RangeBounds<usize>>(&self, pattern: &str, sizes: R) -> Vec<NodeId> {
+        let pattern = pattern.chars().collect::<Vec<_>>();
+        self.walk(ROOT)
+            .into_iter()
+            .map(|(id, _)| id)
+            .filter(|&id| id != ROOT)
+            .filter(|&id| sizes.contains(&self.size(id)))
+            .filter(|&id| glob(&pattern, &self.name(id).chars().collect::<Vec<_>>()))
+            .collect()
     }
 
-    all_dirs.insert(format!("{}/{}", cur_path, current_dir_name), size);
-    size
+    // The n biggest directories, biggest first
+    pub fn largest_dirs(&self, n: usize) -> Vec<NodeId> {
+        let mut dirs = self.dirs();
+        dirs.sort_by_key(|&id| (std::cmp::Reverse(self.size(id)), self.path(id)));
+        dirs.truncate(n);
+        dirs
+    }
+
+    // Groups of files that have the same size but don't all live in the same directory, smallest
+    // size first. Likely copies of each other
+    pub fn same_size_files(&self) -> Vec<(usize, Vec<NodeId>)> {
+        let mut by_size: HashMap<usize, Vec<NodeId>> = HashMap::new();
+        for (id, _) in self.walk(ROOT) {
+            if let Entry::File(file) = &self.nodes[id].entry {
+                by_size.entry(file.size).or_default().push(id);
+            }
+        }
+        let mut out = by_size
+            .into_iter()
+            .filter(|(_, files)| {
+                files
+                    .iter()
+                    .map(|&id| self.parent(id))
+                    .collect::<HashSet<_>>()
+                    .len()
+                    > 1
+            })
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
 }
 
-#[aoc_generator(day7)]
-fn day7_parse(input: &str) -> InputType {
-    let top_level = Rc::new(RefCell::new(Dir::new("", "/")));
-    let mut cwd = vec![top_level.clone()]; //Directory stack
+#[derive(Debug, PartialEq, Eq)]
+pub enum ShellError {
+    UnknownCommand {
+        line: usize,
+        text: String,
+    },
+    // Output that isn't "dir name" or "size name", or that isn't after an ls
+    BadListing {
+        line: usize,
+        text: String,
+    },
+    NotADirectory {
+        line: usize,
+        path: String,
+    },
+    NoSuchEntry {
+        line: usize,
+        path: String,
+    },
+    // A listing (or touch) disagreeing with what we already know about a name
+    Inconsistent {
+        line: usize,
+        path: String,
+        was: String,
+        now: String,
+    },
+    RemoveRoot {
+        line: usize,
+    },
+}
 
-    let mut lines = input.lines().peekable();
+impl fmt::Display for ShellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShellError::UnknownCommand { line, text } => {
+                write!(f, "line {}: unknown command {:?}", line, text)
+            }
+            ShellError::BadListing { line, text } => {
+                write!(f, "line {}: unexpected output {:?}", line, text)
+            }
+            ShellError::NotADirectory { line, path } => {
+                write!(f, "line {}: {} is not a directory", line, path)
+            }
+            ShellError::NoSuchEntry { line, path } => {
+                write!(f, "line {}: {} does not exist", line, path)
+            }
+            ShellError::Inconsistent {
+                line,
+                path,
+                was,
+                now,
+            } => write!(f, "line {}: {} was {} but is now {}", line, path, was, now),
+            ShellError::RemoveRoot { line } => write!(f, "line {}: can't remove /", line),
+        }
+    }
+}
 
-    while let Some(line) = lines.next() {
-        //println!("Current cwd: {:?}", cwd.iter().map(|d| d.as_ref().borrow().name.clone()).collect::<Vec<_>>());
-        let cur_path = cwd
-            .iter()
-            .map(|d| d.as_ref().borrow().name.clone())
-            .collect::<Vec<String>>()
-            .join("/");
-        if line.starts_with("$ cd") {
-            let mut line_spaces = line.split(" ");
-            let dir_name = line_spaces.nth(2).unwrap();
-            //println!("Saw dir name: {}",dir_name);
-            if dir_name == ".." {
-                //If you're going up a directory, pop one off the stack
-                cwd.pop();
-            } else if dir_name == "/" {
-                //If it's the top level, just go to the top
-                cwd.clear();
-                cwd.push(top_level.clone());
-            } else {
-                //If you need to move down a dir, check if it exists first, if it doesn't, create
-                //it and traverse down
-                let next_dir = get_subdir_if_exists(&cwd.last().unwrap().clone(), dir_name);
-
-                if next_dir.is_some() {
-                    cwd.push(next_dir.unwrap().clone());
-                } else {
-                    //println!("Making new dir '{}'",dir_name);
-                    let new_dir = Rc::new(RefCell::new(Dir::new(&cur_path, dir_name)));
-                    let cur_dir = cwd.last_mut().unwrap().clone();
-
-                    cur_dir.as_ref().borrow_mut().dirs.push(new_dir.clone());
-                    cwd.push(new_dir);
+impl Error for ShellError {}
+
+// Replays a terminal session into a FileSystem. On top of the puzzle's cd and ls this
+// understands paths with several segments or a leading /, and three commands that change the
+// tree instead of just looking at it:
+//   $ mkdir path        (parents made as needed)
+//   $ touch size path   (makes the file or changes its size)
+//   $ rm path           (a file or a whole directory)
+pub struct Shell {
+    pub fs: FileSystem,
+    pub cwd: NodeId,
+    listing: bool,
+    line: usize,
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Shell::new()
+    }
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        Shell {
+            fs: FileSystem::new(),
+            cwd: ROOT,
+            listing: false,
+            line: 0,
+        }
+    }
+
+    pub fn run(input: &str) -> Result<FileSystem, ShellError> {
+        let mut shell = Shell::new();
+        for line in input.lines() {
+            shell.execute(line)?;
+        }
+        Ok(shell.fs)
+    }
+
+    fn describe(&self, id: NodeId) -> String {
+        if self.fs.is_dir(id) {
+            "a directory".to_string()
+        } else {
+            format!("a file of {} bytes", self.fs.size(id))
+        }
+    }
+
+    // Walks a path from cwd, making missing directories on the way if asked to (cd into a
+    // directory that was never listed has always just worked)
+    fn walk(&mut self, path: &str, create: bool) -> Result<NodeId, ShellError> {
+        let mut at = if path.starts_with('/') {
+            ROOT
+        } else {
+            self.cwd
+        };
+        for segment in path.split('/') {
+            at = match segment {
+                "" | "." => at,
+                ".." => self.fs.parent(at).unwrap_or(ROOT),
+                name => match self.fs.child(at, name) {
+                    Some(id) => id,
+                    None if create => self.fs.mkdir(at, name),
+                    None => {
+                        return Err(ShellError::NoSuchEntry {
+                            line: self.line,
+                            path: format!("{}/{}", self.fs.path(at).trim_end_matches('/'), name),
+                        })
+                    }
+                },
+            };
+            if !self.fs.is_dir(at) {
+                return Err(ShellError::NotADirectory {
+                    line: self.line,
+                    path: self.fs.path(at),
+                });
+            }
+        }
+        Ok(at)
+    }
+
+    // Splits "a/b/name" into the directory it's in (which has to exist) and "name"
+    fn split<'a>(&mut self, path: &'a str) -> Result<(NodeId, &'a str), ShellError> {
+        match path.rsplit_once('/') {
+            Some(("", name)) => Ok((ROOT, name)),
+            Some((dir, name)) => Ok((self.walk(dir, false)?, name)),
+            None => Ok((self.cwd, path)),
+        }
+    }
+
+    fn entry(&mut self, dir: NodeId, name: &str, size: Option<usize>) -> Result<(), ShellError> {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(ShellError::BadListing {
+                line: self.line,
+                text: name.to_string(),
+            });
+        }
+        let existing = self.fs.child(dir, name);
+        let consistent = match (existing, size) {
+            (None, None) => {
+                self.fs.mkdir(dir, name);
+                true
+            }
+            (None, Some(size)) => {
+                self.fs.add_file(dir, name, size);
+                true
+            }
+            (Some(id), None) => self.fs.is_dir(id),
+            (Some(id), Some(size)) => !self.fs.is_dir(id) && self.fs.size(id) == size,
+        };
+        if consistent {
+            return Ok(());
+        }
+        let id = existing.unwrap();
+        Err(ShellError::Inconsistent {
+            line: self.line,
+            path: self.fs.path(id),
+            was: self.describe(id),
+            now: match size {
+                Some(size) => format!("a file of {} bytes", size),
+                None => "a directory".to_string(),
+            },
+        })
+    }
+
+    pub fn execute(&mut self, line: &str) -> Result<(), ShellError> {
+        self.line += 1;
+        let text = line.trim();
+        let command = match text.strip_prefix('$') {
+            Some(command) => command.trim(),
+            None => {
+                if !self.listing {
+                    return Err(ShellError::BadListing {
+                        line: self.line,
+                        text: text.to_string(),
+                    });
                 }
+                let (first, name) = text.split_once(' ').ok_or(ShellError::BadListing {
+                    line: self.line,
+                    text: text.to_string(),
+                })?;
+                let size = match first {
+                    "dir" => None,
+                    size => Some(size.parse::<usize>().map_err(|_| ShellError::BadListing {
+                        line: self.line,
+                        text: text.to_string(),
+                    })?),
+                };
+                return self.entry(self.cwd, name, size);
             }
-        } else if line.starts_with("$ ls") {
-            //Is a command, get all of the lines until the next command (or end)
-            let mut objects = vec![];
-            let obj_iter = lines.by_ref();
-
-            while let Some(obj) = obj_iter.next_if(|line| !line.starts_with("$")) {
-                objects.push(obj);
-            }
-            //.take_while(|cmd_line| !cmd_line.starts_with("$"))
-            //.collect::<Vec<_>>();
-            for obj in objects {
-                if obj.starts_with("dir") {
-                    //is a directory
-                    let new_dir_name = obj.split(" ").nth(1).unwrap();
-                    // println!("Creating listed dir {}",new_dir_name);
-                    if let Some(_) = get_subdir_if_exists(&cwd.last().unwrap(), new_dir_name)
-                    {
-                        //Don't need to do anything if it exists
-                    } else {
-                        cwd.last_mut()
-                            .unwrap()
-                            .as_ref()
-                            .borrow_mut()
-                            .dirs
-                            .push(Rc::new(RefCell::new(Dir::new(&cur_path, new_dir_name))));
-                    }
-                } else {
-                    //file
-                    let mut file_iter = obj.split(" ");
-                    let size = file_iter.next().unwrap().parse::<usize>().unwrap();
-                    let file_name = file_iter.next().unwrap();
-                    cwd.last_mut()
-                        .unwrap()
-                        .as_ref()
-                        .borrow_mut()
-                        .files
-                        .push(File::new(file_name, size));
+        };
+
+        self.listing = false;
+        let mut words = command.split_whitespace();
+        match (words.next(), words.next(), words.next(), words.next()) {
+            (Some("cd"), Some(path), None, None) => self.cwd = self.walk(path, true)?,
+            (Some("ls"), None, None, None) => self.listing = true,
+            (Some("mkdir"), Some(path), None, None) => {
+                self.walk(path, true)?;
+            }
+            (Some("touch"), Some(size), Some(path), None) => {
+                let size = size
+                    .parse::<usize>()
+                    .map_err(|_| ShellError::UnknownCommand {
+                        line: self.line,
+                        text: text.to_string(),
+                    })?;
+                let (dir, name) = self.split(path)?;
+                match self.fs.child(dir, name) {
+                    Some(id) if !self.fs.is_dir(id) => self.fs.resize(id, size),
+                    _ => self.entry(dir, name, Some(size))?,
+                }
+            }
+            (Some("rm"), Some(path), None, None) => {
+                let (dir, name) = self.split(path.trim_end_matches('/'))?;
+                let id = match name {
+                    "" | "." | ".." => self.walk(path, false)?,
+                    name => self.fs.child(dir, name).ok_or(ShellError::NoSuchEntry {
+                        line: self.line,
+                        path: path.to_string(),
+                    })?,
+                };
+                if id == ROOT {
+                    return Err(ShellError::RemoveRoot { line: self.line });
                 }
+                //Removing where we're standing puts us just above it
+                if self.fs.is_within(self.cwd, id) {
+                    self.cwd = self.fs.parent(id).unwrap();
+                }
+                self.fs.remove(id);
+            }
+            _ => {
+                return Err(ShellError::UnknownCommand {
+                    line: self.line,
+                    text: text.to_string(),
+                })
             }
         }
+        Ok(())
     }
+}
 
-    top_level
+// Going to and from a real directory
+impl FileSystem {
+    // Builds the tree under `root` on disk. Files are made with set_len rather than written, so
+    // they're sparse and a 70MB puzzle input takes up next to nothing
+    pub fn materialise(&self, root: &Path) -> io::Result<()> {
+        fs::create_dir_all(root)?;
+        for (id, _) in self.walk(ROOT).into_iter().skip(1) {
+            let on_disk = root.join(self.path(id).trim_start_matches('/'));
+            match &self.nodes[id].entry {
+                Entry::Dir(_) => fs::create_dir_all(&on_disk)?,
+                Entry::File(file) => fs::File::create(&on_disk)?.set_len(file.size as u64)?,
+            }
+        }
+        Ok(())
+    }
 }
 
-#[aoc(day7, part1)]
-pub fn part1(input: &InputType) -> OutputType {
-    let mut dir_map = BTreeMap::new();
-    calculate_naive_dir_size(&input, &mut dir_map);
+fn sorted_entries(dir: &Path) -> io::Result<Vec<(String, fs::Metadata)>> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().into_string().map_err(|name| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not valid UTF-8", name),
+            )
+        })?;
+        //Links and anything else that isn't a plain file or directory are left out
+        let meta = entry.path().symlink_metadata()?;
+        if meta.is_dir() || meta.is_file() {
+            entries.push((name, meta));
+        }
+    }
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(entries)
+}
+
+// The other way: walks a real directory and writes out the terminal session that would have
+// explored it, in the format day7_parse reads. Handy for big realistic inputs
+pub fn transcript(root: &Path) -> io::Result<String> {
+    fn visit(dir: &Path, out: &mut Vec<String>) -> io::Result<()> {
+        out.push("$ ls".to_string());
+        let entries = sorted_entries(dir)?;
+        for (name, meta) in entries.iter() {
+            if name.contains('\n') || (meta.is_dir() && name.contains(char::is_whitespace)) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{:?} can't be written in a transcript", name),
+                ));
+            }
+            if meta.is_dir() {
+                out.push(format!("dir {}", name));
+            } else {
+                out.push(format!("{} {}", meta.len(), name));
+            }
+        }
+        for (name, _) in entries.iter().filter(|(_, meta)| meta.is_dir()) {
+            out.push(format!("$ cd {}", name));
+            visit(&dir.join(name), out)?;
+            out.push("$ cd ..".to_string());
+        }
+        Ok(())
+    }
+
+    let mut out = vec!["$ cd /".to_string()];
+    visit(root, &mut out)?;
+    Ok(out.join("\n"))
+}
 
-    //println!("{:?}", dir_map);
+// Total file sizes under every directory below `root`, straight from the file metadata and
+// keyed the same way FileSystem::path names them
+pub fn disk_sizes(root: &Path) -> io::Result<BTreeMap<String, usize>> {
+    fn visit(dir: &Path, path: &str, sizes: &mut BTreeMap<String, usize>) -> io::Result<usize> {
+        let mut total = 0;
+        for (name, meta) in sorted_entries(dir)? {
+            total += if meta.is_dir() {
+                let child = format!("{}/{}", path.trim_end_matches('/'), name);
+                visit(&dir.join(&name), &child, sizes)?
+            } else {
+                meta.len() as usize
+            };
+        }
+        sizes.insert(path.to_string(), total);
+        Ok(total)
+    }
 
-    //yea yea filter_map
-    dir_map
+    let mut sizes = BTreeMap::new();
+    visit(root, "/", &mut sizes)?;
+    Ok(sizes)
+}
+
+// How much room there is and how much has to be free once we're done
+pub struct Cleanup {
+    pub capacity: usize,
+    pub required_free: usize,
+    // Allow deleting single files as well as whole directories
+    pub include_files: bool,
+    // The exact search gives up and goes greedy once a subtree has this many partial totals
+    pub max_states: usize,
+}
+
+impl Cleanup {
+    // The puzzle's device: 70000000 total, 30000000 needed for the update
+    pub fn puzzle() -> Self {
+        Cleanup {
+            capacity: 70000000,
+            required_free: 30000000,
+            include_files: false,
+            max_states: 100000,
+        }
+    }
+
+    // Bytes that have to go. If the tree is already bigger than the disk, the overflow has to go
+    // on top of the space we want free
+    pub fn need(&self, fs: &FileSystem) -> usize {
+        (fs.size(ROOT) + self.required_free).saturating_sub(self.capacity)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct DeletionPlan {
+    pub delete: Vec<NodeId>,
+    pub freed: usize,
+    // false if the search was too big and this came from the greedy fallback
+    pub exact: bool,
+}
+
+impl DeletionPlan {
+    // "size path" per line, biggest first
+    pub fn deletion_list(&self, fs: &FileSystem) -> String {
+        let mut delete = self.delete.clone();
+        delete.sort_by_key(|&id| (std::cmp::Reverse(fs.size(id)), fs.path(id)));
+        delete
+            .iter()
+            .map(|&id| format!("{} {}", fs.size(id), fs.path(id)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+// Partial totals reachable inside a subtree, each with what gets deleted to reach it. Anything
+// at or over `need` is as good as done, so only the smallest of those is kept
+type Totals = BTreeMap<usize, Vec<NodeId>>;
+
+fn keep(totals: &mut Totals, need: usize) {
+    let done = totals
+        .range(need..)
+        .map(|(&t, _)| t)
+        .skip(1)
+        .collect::<Vec<_>>();
+    for t in done {
+        totals.remove(&t);
+    }
+}
+
+// Knapsack over the tree: a subtree either goes entirely (the node itself) or contributes any
+// mix of its children's totals. Picking a node rules out everything under it, so nothing ever
+// gets counted twice
+fn exact_totals(fs: &FileSystem, id: NodeId, cleanup: &Cleanup, need: usize) -> Option<Totals> {
+    let mut totals = Totals::new();
+    totals.insert(0, vec![]);
+    if fs.is_dir(id) {
+        for child in fs.children(id) {
+            if !fs.is_dir(child) && !cleanup.include_files {
+                continue;
+            }
+            let child_totals = exact_totals(fs, child, cleanup, need)?;
+            let mut combined = Totals::new();
+            for (a, with_a) in totals.iter() {
+                for (b, with_b) in child_totals.iter() {
+                    combined.entry(a + b).or_insert_with(|| {
+                        let mut both = with_a.clone();
+                        both.extend(with_b.iter().copied());
+                        both
+                    });
+                }
+                if combined.len() > cleanup.max_states {
+                    return None;
+                }
+            }
+            keep(&mut combined, need);
+            totals = combined;
+        }
+    }
+    if id != ROOT {
+        totals.entry(fs.size(id)).or_insert_with(|| vec![id]);
+        keep(&mut totals, need);
+    }
+    Some(totals)
+}
+
+// The best of two cheap answers: the smallest single thing that's big enough on its own (what
+// part2 does), or biggest-first until there's enough, then putting back anything not needed
+fn greedy(fs: &FileSystem, cleanup: &Cleanup, need: usize) -> Option<DeletionPlan> {
+    let candidates = fs
+        .walk(ROOT)
+        .into_iter()
+        .map(|(id, _)| id)
+        .filter(|&id| id != ROOT && (cleanup.include_files || fs.is_dir(id)))
+        .collect::<Vec<_>>();
+
+    let single = candidates
         .iter()
-        .filter(|(_, size)| **size <= 100000)
-        .map(|(_, size)| size)
+        .copied()
+        .filter(|&id| fs.size(id) >= need)
+        .min_by_key(|&id| fs.size(id))
+        .map(|id| (vec![id], fs.size(id)));
+
+    let mut by_size = candidates.clone();
+    by_size.sort_by_key(|&id| std::cmp::Reverse(fs.size(id)));
+    let mut chosen: Vec<NodeId> = vec![];
+    let mut freed = 0;
+    for id in by_size {
+        if freed >= need {
+            break;
+        }
+        if chosen
+            .iter()
+            .any(|&c| fs.is_within(id, c) || fs.is_within(c, id))
+        {
+            continue;
+        }
+        chosen.push(id);
+        freed += fs.size(id);
+    }
+    let combined = if freed >= need {
+        chosen.sort_by_key(|&id| fs.size(id));
+        let mut kept = vec![];
+        for id in chosen {
+            if freed - fs.size(id) >= need {
+                freed -= fs.size(id);
+            } else {
+                kept.push(id);
+            }
+        }
+        Some((kept, freed))
+    } else {
+        None
+    };
+
+    vec![single, combined]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(_, freed)| *freed)
+        .map(|(delete, freed)| DeletionPlan {
+            delete,
+            freed,
+            exact: false,
+        })
+}
+
+// What to delete to get `required_free` bytes free while deleting as little as possible. None
+// if even deleting everything wouldn't do it
+pub fn plan_cleanup(fs: &FileSystem, cleanup: &Cleanup) -> Option<DeletionPlan> {
+    let need = cleanup.need(fs);
+    if need == 0 {
+        return Some(DeletionPlan {
+            delete: vec![],
+            freed: 0,
+            exact: true,
+        });
+    }
+    match exact_totals(fs, ROOT, cleanup, need) {
+        Some(totals) => totals
+            .range(need..)
+            .next()
+            .map(|(&freed, delete)| DeletionPlan {
+                delete: delete.clone(),
+                freed,
+                exact: true,
+            }),
+        None => greedy(fs, cleanup, need),
+    }
+}
+
+#[aoc_generator(day7)]
+fn day7_parse(input: &str) -> InputType {
+    Shell::run(input).unwrap()
+}
+
+#[aoc(day7, part1)]
+pub fn part1(input: &InputType) -> OutputType {
+    input
+        .dirs()
+        .into_iter()
+        .map(|dir| input.size(dir))
+        .filter(|&size| size <= 100000)
         .sum()
 }
 
 #[aoc(day7, part2)]
 pub fn part2(input: &InputType) -> OutputType {
-    let mut dir_map = BTreeMap::new();
-    calculate_naive_dir_size(&input, &mut dir_map);
-
-    static TOTAL_SIZE: usize = 70000000;
-    static NEEDED_SPACE: usize = 30000000;
-    let current_used = dir_map.get("//").unwrap();
+    //The puzzle only ever deletes one directory
+    let need_to_delete = Cleanup::puzzle().need(input);
 
-    let unused_space = TOTAL_SIZE - current_used;
-    let need_to_delete = NEEDED_SPACE - unused_space;
-
-    *dir_map
-        .iter()
-        .map(|(_, size)| size)
-        .filter(|&size| *size >= need_to_delete)
+    input
+        .dirs()
+        .into_iter()
+        .map(|dir| input.size(dir))
+        .filter(|&size| size >= need_to_delete)
         .min()
         .unwrap()
 }
@@ -241,4 +960,272 @@ $ ls
     fn day7_part2() {
         assert_eq!(part2(&day7_parse(get_test_input())), 24933642);
     }
+
+    #[test]
+    fn day7_paths() {
+        let fs = day7_parse(get_test_input());
+        let e = fs.resolve(ROOT, "/a/e").unwrap();
+        assert_eq!(fs.path(e), "/a/e");
+        assert_eq!(fs.path(ROOT), "/");
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.size(fs.resolve(ROOT, "a").unwrap()), 94853);
+        assert_eq!(fs.size(ROOT), 48381165);
+
+        assert_eq!(
+            fs.resolve(e, "../../d/j").map(|j| fs.path(j)),
+            Some("/d/j".to_string())
+        );
+        assert_eq!(fs.resolve(e, "/../../a/./e/"), Some(e));
+        assert_eq!(fs.resolve(e, "../nope"), None);
+        assert_eq!(fs.resolve(e, "i/deeper"), None);
+
+        let paths = fs
+            .dirs()
+            .into_iter()
+            .map(|d| fs.path(d))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/", "/a", "/a/e", "/d"]);
+    }
+
+    #[test]
+    fn day7_shell() {
+        let fs = Shell::run(
+            "$ cd /
+$ cd ..
+$ cd a/b/c
+$ ls
+10 x
+dir y
+$ cd /a/b
+$ ls
+dir c
+20 z
+$ mkdir /q/r
+$ touch 5 /q/r/s
+$ touch 7 c/y/t
+$ touch 15 c/x
+$ cd c/y
+$ rm /a/b/c
+$ ls
+20 z",
+        )
+        .unwrap();
+        let paths = fs
+            .dirs()
+            .into_iter()
+            .map(|d| fs.path(d))
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/", "/a", "/a/b", "/q", "/q/r"]);
+        assert_eq!(fs.size(ROOT), 25);
+        assert_eq!(fs.size(fs.resolve(ROOT, "/a").unwrap()), 20);
+
+        let err = |input| Shell::run(input).unwrap_err();
+        assert_eq!(
+            err("$ ls\n10 x\n$ ls\n11 x"),
+            ShellError::Inconsistent {
+                line: 4,
+                path: "/x".to_string(),
+                was: "a file of 10 bytes".to_string(),
+                now: "a file of 11 bytes".to_string()
+            }
+        );
+        assert_eq!(
+            err("$ ls\ndir x\n$ touch 3 x"),
+            ShellError::Inconsistent {
+                line: 3,
+                path: "/x".to_string(),
+                was: "a directory".to_string(),
+                now: "a file of 3 bytes".to_string()
+            }
+        );
+        assert_eq!(
+            err("$ touch 3 x\n$ cd x"),
+            ShellError::NotADirectory {
+                line: 2,
+                path: "/x".to_string()
+            }
+        );
+        assert_eq!(
+            err("$ rm a"),
+            ShellError::NoSuchEntry {
+                line: 1,
+                path: "a".to_string()
+            }
+        );
+        assert_eq!(err("$ rm /"), ShellError::RemoveRoot { line: 1 });
+        assert_eq!(
+            err("$ cd a\n10 x"),
+            ShellError::BadListing {
+                line: 2,
+                text: "10 x".to_string()
+            }
+        );
+        assert_eq!(
+            err("$ pwd"),
+            ShellError::UnknownCommand {
+                line: 1,
+                text: "$ pwd".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn day7_reports() {
+        let fs = day7_parse(get_test_input());
+        assert_eq!(
+            fs.tree(ROOT),
+            "- / (dir, size=48381165)
+  - a (dir, size=94853)
+    - e (dir, size=584)
+      - i (file, size=584)
+    - f (file, size=29116)
+    - g (file, size=2557)
+    - h.lst (file, size=62596)
+  - b.txt (file, size=14848514)
+  - c.dat (file, size=8504156)
+  - d (dir, size=24933642)
+    - d.ext (file, size=5626152)
+    - d.log (file, size=8033020)
+    - j (file, size=4060174)
+    - k (file, size=7214296)"
+        );
+
+        let du = |depth| fs.du(depth);
+        assert_eq!(du(0), vec![("/".to_string(), 48381165)]);
+        assert_eq!(
+            du(5),
+            vec![
+                ("/a/e".to_string(), 584),
+                ("/a".to_string(), 94853),
+                ("/d".to_string(), 24933642),
+                ("/".to_string(), 48381165)
+            ]
+        );
+
+        let paths = |ids: Vec<NodeId>| ids.into_iter().map(|id| fs.path(id)).collect::<Vec<_>>();
+        assert_eq!(paths(fs.find("d*", ..)), vec!["/d", "/d/d.ext", "/d/d.log"]);
+        assert_eq!(paths(fs.find("?", ..1000)), vec!["/a/e", "/a/e/i"]);
+        assert_eq!(
+            paths(fs.find("*", 5000000..=8600000)),
+            vec!["/c.dat", "/d/d.ext", "/d/d.log", "/d/k"]
+        );
+        assert_eq!(paths(fs.largest_dirs(2)), vec!["/", "/d"]);
+        assert!(fs.same_size_files().is_empty());
+
+        //a and b share a directory, but x/c makes the 10s a group; the 20s are both in x/y
+        let fs =
+            Shell::run("$ mkdir x/y\n$ touch 10 a\n$ touch 10 b\n$ touch 10 x/c\n$ touch 20 x/y/d\n$ touch 20 x/y/e")
+                .unwrap();
+        let groups = fs
+            .same_size_files()
+            .into_iter()
+            .map(|(size, ids)| {
+                (
+                    size,
+                    ids.into_iter().map(|id| fs.path(id)).collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            groups,
+            vec![(
+                10,
+                vec!["/a".to_string(), "/b".to_string(), "/x/c".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn day7_real_directory() {
+        let root = std::env::temp_dir().join(format!("aoc2022-day7-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let tree = day7_parse(get_test_input());
+        tree.materialise(&root).unwrap();
+        //Add an empty directory too, those have to survive the trip as well
+        fs::create_dir_all(root.join("d/empty")).unwrap();
+
+        let sizes = disk_sizes(&root).unwrap();
+        for dir in tree.dirs() {
+            assert_eq!(sizes[&tree.path(dir)], tree.size(dir));
+        }
+        let part1_on_disk: usize = sizes.values().filter(|&&size| size <= 100000).sum();
+        assert_eq!(part1_on_disk, part1(&tree));
+
+        let text = transcript(&root).unwrap();
+        let back = day7_parse(&text);
+        assert_eq!(back.tree(ROOT), {
+            let mut expected = Shell::run(get_test_input()).unwrap();
+            let d = expected.resolve(ROOT, "/d").unwrap();
+            expected.mkdir(d, "empty");
+            expected.tree(ROOT)
+        });
+        assert_eq!(part1(&back), 95437);
+        assert_eq!(part2(&back), 24933642);
+        assert!(
+            text.starts_with("$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d\n$ cd a")
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn day7_cleanup() {
+        let fs = day7_parse(get_test_input());
+        let mut cleanup = Cleanup::puzzle();
+        let plan = plan_cleanup(&fs, &cleanup).unwrap();
+        assert_eq!(plan.freed, part2(&fs));
+        assert_eq!(plan.deletion_list(&fs), "24933642 /d");
+
+        //c.dat on its own is enough and smaller than /d
+        cleanup.include_files = true;
+        let plan = plan_cleanup(&fs, &cleanup).unwrap();
+        assert_eq!(plan.deletion_list(&fs), "8504156 /c.dat");
+        assert!(plan.exact);
+
+        //Nothing to do with enough room, nothing to be done without any
+        cleanup.capacity = 100000000;
+        assert_eq!(plan_cleanup(&fs, &cleanup).unwrap().delete, vec![]);
+        cleanup.capacity = 48381165;
+        cleanup.required_free = 48381166;
+        assert_eq!(plan_cleanup(&fs, &cleanup), None);
+
+        //Two directories beat the biggest one plus whatever it takes to top it up; 105 bytes
+        //have to go
+        let fs = Shell::run(
+            "$ mkdir x/w
+$ mkdir y
+$ mkdir z
+$ touch 30 x/w/f1
+$ touch 30 x/f2
+$ touch 50 y/f3
+$ touch 100 z/f4",
+        )
+        .unwrap();
+        let mut cleanup = Cleanup {
+            capacity: 300,
+            required_free: 195,
+            include_files: false,
+            max_states: 1000,
+        };
+        assert_eq!(cleanup.need(&fs), 105);
+        let plan = plan_cleanup(&fs, &cleanup).unwrap();
+        assert_eq!(plan.deletion_list(&fs), "60 /x\n50 /y");
+        assert!(plan.exact);
+
+        cleanup.max_states = 1;
+        let plan = plan_cleanup(&fs, &cleanup).unwrap();
+        assert!(!plan.exact);
+        assert_eq!(plan.deletion_list(&fs), "100 /z\n60 /x");
+
+        //210 bytes on a 200 byte disk: 10 over, plus the 30 we want free. /x/w's 30 alone would
+        //still leave only 20 free
+        cleanup.capacity = 200;
+        cleanup.required_free = 30;
+        assert_eq!(cleanup.need(&fs), 40);
+        assert_eq!(
+            plan_cleanup(&fs, &cleanup).unwrap().deletion_list(&fs),
+            "50 /y"
+        );
+    }
 }
@@ -0,0 +1,268 @@
+use std::cmp::{max, min};
+use std::iter::FromIterator;
+
+// Closed integer intervals, [start, end] with both ends included. Shared by anything that works
+// on ranges of sections/columns (day4's assignments, day15's sensor coverage per row)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Interval {
+    pub fn new(start: i64, end: i64) -> Self {
+        assert!(start <= end, "Backwards interval {}-{}", start, end);
+        Interval { start, end }
+    }
+
+    // Number of integers covered, never zero since both ends are included
+    pub fn size(&self) -> u64 {
+        (self.end - self.start) as u64 + 1
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        self.start <= x && x <= self.end
+    }
+
+    pub fn contains_interval(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        if self.overlaps(other) {
+            Some(Interval::new(
+                max(self.start, other.start),
+                min(self.end, other.end),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+// A set of integers stored as sorted, disjoint intervals. Touching intervals get merged too
+// ([1, 3] and [4, 5] become [1, 5]) since there is no integer between them, so every set has
+// exactly one representation and equality just works.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+
+    pub fn insert(&mut self, iv: Interval) {
+        //Everything that ends before we start (and doesn't touch) stays on the left, everything
+        //that starts after we end stays on the right, anything in between gets swallowed
+        let left = self
+            .intervals
+            .partition_point(|other| other.end < iv.start - 1);
+        let right = self
+            .intervals
+            .partition_point(|other| other.start <= iv.end + 1);
+        let merged = if left < right {
+            Interval::new(
+                min(iv.start, self.intervals[left].start),
+                max(iv.end, self.intervals[right - 1].end),
+            )
+        } else {
+            iv
+        };
+        self.intervals.splice(left..right, [merged]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // Total number of integers in the set
+    pub fn covered_len(&self) -> u64 {
+        self.intervals.iter().map(|iv| iv.size()).sum()
+    }
+
+    fn find(&self, x: i64) -> Option<&Interval> {
+        let idx = self.intervals.partition_point(|iv| iv.end < x);
+        self.intervals.get(idx).filter(|iv| iv.contains(x))
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        self.find(x).is_some()
+    }
+
+    // The whole interval is in the set
+    pub fn contains_interval(&self, iv: &Interval) -> bool {
+        self.find(iv.start)
+            .map(|found| found.contains_interval(iv))
+            .unwrap_or(false)
+    }
+
+    // Any part of the interval is in the set
+    pub fn overlaps(&self, iv: &Interval) -> bool {
+        let idx = self.intervals.partition_point(|other| other.end < iv.start);
+        self.intervals
+            .get(idx)
+            .map(|other| other.overlaps(iv))
+            .unwrap_or(false)
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut out = self.clone();
+        for iv in other.iter() {
+            out.insert(*iv);
+        }
+        out
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        //Both lists are sorted, so walk them together
+        let mut out = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (&self.intervals[i], &other.intervals[j]);
+            if let Some(both) = a.intersection(b) {
+                out.push(both);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals: out }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut out = vec![];
+        for iv in self.intervals.iter() {
+            let mut start = iv.start;
+            for cut in other.intervals.iter() {
+                if cut.end < start {
+                    continue;
+                }
+                if cut.start > iv.end {
+                    break;
+                }
+                if cut.start > start {
+                    out.push(Interval::new(start, cut.start - 1));
+                }
+                start = cut.end + 1;
+                if start > iv.end {
+                    break;
+                }
+            }
+            if start <= iv.end {
+                out.push(Interval::new(start, iv.end));
+            }
+        }
+        IntervalSet { intervals: out }
+    }
+}
+
+impl From<Interval> for IntervalSet {
+    fn from(iv: Interval) -> Self {
+        IntervalSet {
+            intervals: vec![iv],
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for iv in iter {
+            set.insert(iv);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn set(ivs: &[(i64, i64)]) -> IntervalSet {
+        ivs.iter().map(|(a, b)| Interval::new(*a, *b)).collect()
+    }
+
+    fn as_tuples(s: &IntervalSet) -> Vec<(i64, i64)> {
+        s.iter().map(|iv| (iv.start, iv.end)).collect()
+    }
+
+    #[test]
+    fn interval_basics() {
+        let a = Interval::new(2, 8);
+        let b = Interval::new(3, 7);
+        let c = Interval::new(8, 9);
+        assert_eq!(a.size(), 7);
+        assert!(a.contains_interval(&b));
+        assert!(!b.contains_interval(&a));
+        assert!(a.overlaps(&c));
+        assert!(!b.overlaps(&c));
+        assert_eq!(a.intersection(&c), Some(Interval::new(8, 8)));
+        assert_eq!(b.intersection(&c), None);
+    }
+
+    #[test]
+    fn interval_set_merging() {
+        //Overlapping, touching and swallowed intervals all end up merged
+        let s = set(&[
+            (10, 12),
+            (1, 3),
+            (5, 6),
+            (4, 4),
+            (20, 30),
+            (22, 25),
+            (15, 16),
+        ]);
+        assert_eq!(as_tuples(&s), vec![(1, 6), (10, 12), (15, 16), (20, 30)]);
+        assert_eq!(s.covered_len(), 6 + 3 + 2 + 11);
+        assert!(s.contains(4));
+        assert!(!s.contains(13));
+        assert!(s.contains_interval(&Interval::new(21, 29)));
+        assert!(!s.contains_interval(&Interval::new(11, 15)));
+        assert!(s.overlaps(&Interval::new(13, 15)));
+        assert!(!s.overlaps(&Interval::new(13, 14)));
+
+        let mut all = s.clone();
+        all.insert(Interval::new(-5, 100));
+        assert_eq!(as_tuples(&all), vec![(-5, 100)]);
+    }
+
+    #[test]
+    fn interval_set_algebra() {
+        let a = set(&[(1, 10), (20, 30)]);
+        let b = set(&[(5, 25), (28, 40)]);
+        assert_eq!(as_tuples(&a.union(&b)), vec![(1, 40)]);
+        assert_eq!(
+            as_tuples(&a.intersection(&b)),
+            vec![(5, 10), (20, 25), (28, 30)]
+        );
+        assert_eq!(as_tuples(&a.difference(&b)), vec![(1, 4), (26, 27)]);
+        assert_eq!(as_tuples(&b.difference(&a)), vec![(11, 19), (31, 40)]);
+        assert!(a.difference(&a).is_empty());
+
+        //Check against plain old sets of integers
+        for x in -5..50 {
+            assert_eq!(a.union(&b).contains(x), a.contains(x) || b.contains(x));
+            assert_eq!(
+                a.intersection(&b).contains(x),
+                a.contains(x) && b.contains(x)
+            );
+            assert_eq!(
+                a.difference(&b).contains(x),
+                a.contains(x) && !b.contains(x)
+            );
+        }
+    }
+}
@@ -3,6 +3,8 @@ extern crate aoc_runner;
 #[macro_use]
 extern crate aoc_runner_derive;
 
+pub mod interval;
+
 pub mod day1;
 pub mod day2;
 pub mod day3;
@@ -19,5 +21,9 @@ pub mod day13;
 pub mod day14;
 pub mod day15;
 pub mod day16;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
 
 aoc_lib! { year = 2022 }